@@ -0,0 +1,72 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Benchmark of the map-producing breakdowns on a wide tree with many distinct extensions.
+//!
+//! Run with `cargo bench --bench breakdown`.
+
+use dir_size::{get_size_by_extension, get_size_by_file_kind, get_size_in_bytes};
+use std::{
+    env, fs, io,
+    path::Path,
+    process,
+    time::{Duration, Instant},
+};
+
+const DIRS: usize = 256;
+const FILES_PER_DIR: usize = 64;
+const EXTENSIONS: usize = 4096;
+const ITERATIONS: usize = 10;
+
+fn build_tree(root: &Path) -> io::Result<()> {
+    for dir in 0..DIRS {
+        let dir_path = root.join(format!("dir{dir}"));
+        fs::create_dir_all(&dir_path)?;
+        for file in 0..FILES_PER_DIR {
+            let extension = (dir * FILES_PER_DIR + file) % EXTENSIONS;
+            fs::write(dir_path.join(format!("file{file}.ext{extension}")), [0; 16])?;
+        }
+    }
+    Ok(())
+}
+
+fn bench<T>(name: &str, mut f: impl FnMut() -> T) {
+    // Warm up the page cache before measuring.
+    f();
+
+    let mut timings: Vec<Duration> = (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .collect();
+    timings.sort();
+
+    println!(
+        "{name}: median {:?}, min {:?}, max {:?}",
+        timings[ITERATIONS / 2],
+        timings[0],
+        timings[ITERATIONS - 1]
+    );
+}
+
+fn main() -> io::Result<()> {
+    let root = env::temp_dir().join(format!("dir-size-bench-{}", process::id()));
+    build_tree(&root)?;
+
+    println!(
+        "{} files in {DIRS} directories, {EXTENSIONS} distinct extensions",
+        DIRS * FILES_PER_DIR
+    );
+    bench("get_size_in_bytes", || get_size_in_bytes(&root).unwrap());
+    bench("get_size_by_extension", || {
+        get_size_by_extension(&root).unwrap()
+    });
+    bench("get_size_by_file_kind", || {
+        get_size_by_file_kind(&root).unwrap()
+    });
+
+    fs::remove_dir_all(&root)
+}
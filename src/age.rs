@@ -0,0 +1,193 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    get_size_with_options,
+    walk::{walk, Visitor},
+    SizeOptions,
+};
+use std::{
+    fs::{self, Metadata},
+    io,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+/// Get the total size (in bytes) of the files last modified within `[start, end)`.
+///
+/// If `path` points to a directory, all of its files and the files of its subdirectories
+/// are considered. `start` is inclusive and `end` is exclusive, so consecutive windows
+/// never count a file twice. Files whose modification time is not available are not counted.
+/// This is the same as combining [`SizeOptions::modified_after`] and
+/// [`SizeOptions::modified_before`]. Runs in bounded memory.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_size_in_window(path: &Path, start: SystemTime, end: SystemTime) -> io::Result<u64> {
+    let options = SizeOptions::new()
+        .modified_after(start)
+        .modified_before(end);
+    Ok(get_size_with_options(path, &options)?.bytes)
+}
+
+/// Get the total size (in bytes) of the files not modified for more than `age`.
+///
+/// If `path` points to a directory, all of its files and the files of its subdirectories
+/// are considered. The age of a file is measured against [`SystemTime::now`], read once when
+/// the walk starts, and a file counts if `now - mtime > age`. Files with a modification time
+/// in the future have a negative age, so they are never counted, and neither are files whose
+/// modification time is not available. Runs in bounded memory.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_size_older_than(path: &Path, age: Duration) -> io::Result<u64> {
+    let Some(cutoff) = SystemTime::now().checked_sub(age) else {
+        // No representable modification time is that old.
+        fs::symlink_metadata(path)?;
+        return Ok(0);
+    };
+    let options = SizeOptions::new().modified_before(cutoff);
+    Ok(get_size_with_options(path, &options)?.bytes)
+}
+
+/// Get the total size (in bytes) of the files in each age bucket, from the newest to the oldest.
+///
+/// `buckets` holds the upper bound of the age of each bucket, e.g. a day, a week and a month,
+/// and the result has one more entry than `buckets`, for the files older than all of them.
+/// Each file goes to the first bucket whose bound its age is strictly below, so the bounds
+/// are expected in ascending order, and a file exactly a day old goes to the bucket after the
+/// one bounded by a day, in line with [`get_size_older_than`]. The age of a file is measured
+/// against [`SystemTime::now`], read once when the walk starts. Files with a modification time
+/// in the future count as modified now, in the first bucket with a non-zero bound, while files
+/// whose modification time is not available go to the last entry, along with the oldest ones.
+///
+/// If `path` points to a directory, all of its files and the files of its subdirectories
+/// are considered. Memory grows with the number of buckets.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_size_by_age(path: &Path, buckets: &[Duration]) -> io::Result<Vec<u64>> {
+    walk(
+        path,
+        &AgeBuckets {
+            now: SystemTime::now(),
+            buckets,
+        },
+    )
+}
+
+struct AgeBuckets<'a> {
+    now: SystemTime,
+    buckets: &'a [Duration],
+}
+
+impl Visitor for AgeBuckets<'_> {
+    type Acc = Vec<u64>;
+
+    fn init(&self) -> Vec<u64> {
+        vec![0; self.buckets.len() + 1]
+    }
+
+    fn visit_file(&self, acc: &mut Vec<u64>, _path: &Path, meta: &Metadata, size: u64) {
+        let bucket = meta.modified().map_or(self.buckets.len(), |modified| {
+            // Files from the future have an error here, and count as modified now.
+            let age = self.now.duration_since(modified).unwrap_or_default();
+            self.buckets
+                .iter()
+                .position(|&bound| age < bound)
+                .unwrap_or(self.buckets.len())
+        });
+        acc[bucket] += size;
+    }
+
+    fn merge(&self, mut a: Vec<u64>, b: Vec<u64>) -> Vec<u64> {
+        a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn aged_tree(ages: &[(&str, u64, u64)]) -> (tempfile::TempDir, SystemTime) {
+        let dir = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        for &(name, size, age) in ages {
+            let path: PathBuf = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let file = fs::File::create(path).unwrap();
+            file.set_len(size).unwrap();
+            file.set_modified(now - Duration::from_secs(age)).unwrap();
+        }
+        (dir, now)
+    }
+
+    #[test]
+    fn test_get_size_in_window() {
+        let (dir, now) = aged_tree(&[("a", 1, 10), ("sub/b", 10, 20), ("c", 100, 30)]);
+        let ago = |secs| now - Duration::from_secs(secs);
+
+        assert_eq!(get_size_in_window(dir.path(), ago(25), ago(5)).unwrap(), 11);
+        // The start is inclusive and the end is exclusive.
+        assert_eq!(
+            get_size_in_window(dir.path(), ago(20), ago(10)).unwrap(),
+            10
+        );
+        assert_eq!(get_size_in_window(dir.path(), ago(5), now).unwrap(), 0);
+        assert_eq!(get_size_in_window(dir.path(), ago(5), ago(25)).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_get_size_older_than() {
+        let day = 24 * 60 * 60;
+        let (dir, _) = aged_tree(&[
+            ("new", 1, day),
+            ("sub/old", 10, 100 * day),
+            ("older", 100, 200 * day),
+        ]);
+        let future = fs::File::create(dir.path().join("future")).unwrap();
+        future.set_len(1000).unwrap();
+        future
+            .set_modified(SystemTime::now() + Duration::from_secs(day))
+            .unwrap();
+
+        let days = |days| Duration::from_secs(days * day);
+        assert_eq!(get_size_older_than(dir.path(), days(90)).unwrap(), 110);
+        assert_eq!(get_size_older_than(dir.path(), days(150)).unwrap(), 100);
+        assert_eq!(
+            get_size_older_than(dir.path(), Duration::ZERO).unwrap(),
+            111
+        );
+        assert_eq!(get_size_older_than(dir.path(), Duration::MAX).unwrap(), 0);
+        assert!(get_size_older_than(&dir.path().join("missing"), Duration::MAX).is_err());
+    }
+
+    #[test]
+    fn test_get_size_by_age() {
+        let day = 24 * 60 * 60;
+        let (dir, now) = aged_tree(&[
+            ("hour", 1, 60 * 60),
+            ("sub/day", 10, day),
+            ("days", 100, 3 * day),
+            ("sub/weeks", 1000, 20 * day),
+            ("years", 10000, 1000 * day),
+        ]);
+        let future = fs::File::create(dir.path().join("future")).unwrap();
+        future.set_len(100000).unwrap();
+        future.set_modified(now + Duration::from_secs(day)).unwrap();
+
+        let days = |days| Duration::from_secs(days * day);
+        assert_eq!(
+            get_size_by_age(dir.path(), &[days(1), days(7), days(30)]).unwrap(),
+            [100001, 110, 1000, 10000]
+        );
+        assert_eq!(get_size_by_age(dir.path(), &[]).unwrap(), [111111]);
+        assert_eq!(
+            get_size_by_age(dir.path(), &[Duration::ZERO, days(2)]).unwrap(),
+            [0, 100011, 11100]
+        );
+    }
+}
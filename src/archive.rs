@@ -0,0 +1,138 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::Error;
+use flate2::read::GzDecoder;
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+/// Get the total uncompressed size of the entries of an archive (in bytes), without extracting it.
+///
+/// This is how much space extracting the archive needs for the data of its files, not counting
+/// the space taken by directories or any filesystem overhead. The format is determined by the
+/// extension of `path`, regardless of case:
+/// - `.zip`: the sizes are read from the central directory at the end of the archive,
+///   so the data of the entries is not read at all.
+/// - `.tar`: the sizes are read from the header of each entry, seeking over their data.
+/// - `.tar.gz` and `.tgz`: gzip has no index, so the whole archive has to be decompressed
+///   to reach the header of each entry, although nothing is written to disk.
+///
+/// The sizes are those recorded in the archive, which are trusted as is.
+/// Runs in bounded memory, apart from the central directory of zip archives.
+///
+/// This function will return an error if `path` does not exist, cannot be read, is not a valid
+/// archive, or has an extension other than the supported ones, and [`Error::Overflow`] if the
+/// recorded sizes add up to more than 64 bits.
+pub fn get_archive_uncompressed_size(path: &Path) -> Result<u64, Error> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if name.ends_with(".zip") {
+        zip_size(File::open(path)?)
+    } else if name.ends_with(".tar") {
+        tar_size(tar::Archive::new(File::open(path)?).entries_with_seek()?)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        let decoder = GzDecoder::new(BufReader::new(File::open(path)?));
+        tar_size(tar::Archive::new(decoder).entries()?)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "unsupported archive format, expected .tar, .tar.gz, .tgz or .zip",
+        )
+        .into())
+    }
+}
+
+fn zip_size(file: File) -> Result<u64, Error> {
+    let mut archive = zip::ZipArchive::new(BufReader::new(file)).map_err(io::Error::from)?;
+    (0..archive.len()).try_fold(0u64, |total, i| {
+        let entry = archive.by_index_raw(i).map_err(io::Error::from)?;
+        checked_total(total, entry.size())
+    })
+}
+
+fn tar_size<R: Read>(entries: tar::Entries<'_, R>) -> Result<u64, Error> {
+    entries
+        .into_iter()
+        .try_fold(0u64, |total, entry| checked_total(total, entry?.size()))
+}
+
+/// Add `size` to `total`, failing on overflow since such sizes cannot be valid.
+fn checked_total(total: u64, size: u64) -> Result<u64, Error> {
+    total.checked_add(size).ok_or(Error::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::{fs, io::Write};
+
+    const ENTRIES: [(&str, usize); 3] = [("a", 100), ("dir/b", 2000), ("dir/c", 0)];
+
+    fn tar(writer: impl Write) -> impl Write {
+        let mut builder = tar::Builder::new(writer);
+        for (name, size) in ENTRIES {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(size as u64);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, &vec![0; size][..])
+                .unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_get_archive_uncompressed_size() {
+        let dir = tempfile::tempdir().unwrap();
+
+        tar(File::create(dir.path().join("a.tar")).unwrap());
+        let gz = tar(GzEncoder::new(
+            File::create(dir.path().join("a.TAR.GZ")).unwrap(),
+            Compression::default(),
+        ));
+        drop(gz);
+        fs::copy(dir.path().join("a.TAR.GZ"), dir.path().join("a.tgz")).unwrap();
+
+        let mut zip = zip::ZipWriter::new(File::create(dir.path().join("a.zip")).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.add_directory("dir", options).unwrap();
+        for (name, size) in ENTRIES {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(&vec![0; size]).unwrap();
+        }
+        zip.finish().unwrap();
+
+        for name in ["a.tar", "a.TAR.GZ", "a.tgz", "a.zip"] {
+            assert_eq!(
+                get_archive_uncompressed_size(&dir.path().join(name)).unwrap(),
+                2100,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_archive_uncompressed_size_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rar"), [0; 10]).unwrap();
+        fs::write(dir.path().join("a.zip"), [0; 10]).unwrap();
+
+        let error = |name| match get_archive_uncompressed_size(&dir.path().join(name)).unwrap_err()
+        {
+            Error::Io(e) => e.kind(),
+            e => panic!("unexpected error: {e}"),
+        };
+        assert_eq!(error("a.rar"), io::ErrorKind::InvalidInput);
+        assert_eq!(error("missing.zip"), io::ErrorKind::NotFound);
+        assert_eq!(error("a.zip"), io::ErrorKind::InvalidData);
+        assert!(matches!(checked_total(u64::MAX, 1), Err(Error::Overflow)));
+    }
+}
@@ -0,0 +1,102 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::get_size_in_bytes;
+use std::{io, path::Path};
+
+/// The current size of a file compared to a previously recorded one.
+///
+/// With the `serde` feature, it can be serialized so that `current` is used as the
+/// baseline of the next run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SizeDelta {
+    /// The previously recorded size (in bytes).
+    pub baseline: u64,
+    /// The current size (in bytes).
+    pub current: u64,
+}
+
+impl SizeDelta {
+    /// Growth since the baseline (in bytes), negative if the file shrank.
+    ///
+    /// Saturates at `i64::MIN` and `i64::MAX` instead of overflowing.
+    pub fn delta(&self) -> i64 {
+        let delta = i128::from(self.current) - i128::from(self.baseline);
+        delta.clamp(i64::MIN.into(), i64::MAX.into()) as i64
+    }
+}
+
+/// Get how much the size of the file (in bytes) changed since `baseline`.
+///
+/// Returns the current size minus `baseline`, see [`SizeDelta::delta`].
+/// Runs in bounded memory.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn size_since_baseline(path: &Path, baseline: u64) -> io::Result<i64> {
+    Ok(size_with_baseline(path, baseline)?.delta())
+}
+
+/// Get the size of the file (in bytes) along with `baseline`, to compute the change in between.
+///
+/// Runs in bounded memory.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn size_with_baseline(path: &Path, baseline: u64) -> io::Result<SizeDelta> {
+    Ok(SizeDelta {
+        baseline,
+        current: get_size_in_bytes(path)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_size_since_baseline() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file"), [0; 100]).unwrap();
+
+        assert_eq!(size_since_baseline(dir.path(), 40).unwrap(), 60);
+        assert_eq!(size_since_baseline(dir.path(), 150).unwrap(), -50);
+        assert_eq!(
+            size_with_baseline(dir.path(), 100).unwrap(),
+            SizeDelta {
+                baseline: 100,
+                current: 100
+            }
+        );
+    }
+
+    #[test]
+    fn test_delta_saturates() {
+        let delta = SizeDelta {
+            baseline: 0,
+            current: u64::MAX,
+        };
+        assert_eq!(delta.delta(), i64::MAX);
+
+        let delta = SizeDelta {
+            baseline: u64::MAX,
+            current: 0,
+        };
+        assert_eq!(delta.delta(), i64::MIN);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let delta = SizeDelta {
+            baseline: 1,
+            current: 2,
+        };
+        let json = serde_json::to_string(&delta).unwrap();
+        assert_eq!(json, r#"{"baseline":1,"current":2}"#);
+        assert_eq!(serde_json::from_str::<SizeDelta>(&json).unwrap(), delta);
+    }
+}
@@ -0,0 +1,337 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    walk::{Visitor, Walker},
+    Error, SizeOptions,
+};
+use std::{collections::HashMap, fs::Metadata, hash::Hash, io, path::Path};
+
+/// Extensions of compression formats, which are usually appended to the extension of another format.
+const COMPRESSION_EXTENSIONS: [&str; 9] =
+    ["br", "bz2", "gz", "lz", "lz4", "lzma", "xz", "z", "zst"];
+
+/// How the extension of a file is determined when grouping sizes by extension.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExtensionMode {
+    /// Only the last extension, e.g. `gz` for `archive.tar.gz`. This is the default.
+    #[default]
+    Single,
+    /// The last two extensions if the last one is a compression format, e.g. `tar.gz` for
+    /// `archive.tar.gz` or `svg.gz` for `image.svg.gz`, and the last extension otherwise.
+    ///
+    /// Only compression formats are joined with the preceding extension,
+    /// so `v1.2.txt` still has the extension `txt`.
+    Compound,
+}
+
+/// The type of an entry, as grouped by [`get_size_by_file_kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FileKind {
+    /// A regular file.
+    File,
+    /// A symlink, which is not followed.
+    Symlink,
+    /// Any other entry that is not a directory, such as a FIFO, a socket or a device.
+    Other,
+}
+
+/// Get the total size of files (in bytes), grouped by their last file extension.
+///
+/// Works like [`get_size_by_extension_with`] using [`ExtensionMode::Single`].
+pub fn get_size_by_extension(path: &Path) -> io::Result<HashMap<String, u64>> {
+    get_size_by_extension_with(path, ExtensionMode::Single)
+}
+
+/// Get the total size of files (in bytes), grouped by file extension as determined by `mode`.
+///
+/// Extensions are lowercased, so `a.TXT` and `b.txt` are grouped together,
+/// and files without an extension are grouped under the empty string `""`.
+/// If `path` points to a directory, all of its files and subdirectories are included.
+/// Memory grows with the number of distinct extensions, not with the number of files.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_size_by_extension_with(
+    path: &Path,
+    mode: ExtensionMode,
+) -> io::Result<HashMap<String, u64>> {
+    group_sizes_by(path, |path, _| extension_of(path, mode))
+}
+
+/// Get the total size of files (in bytes), grouped by the user ID of their owner.
+///
+/// If `path` points to a directory, all of its files and subdirectories are included.
+/// Memory grows with the number of distinct owners, not with the number of files.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+#[cfg(unix)]
+pub fn get_size_by_owner(path: &Path) -> io::Result<HashMap<u32, u64>> {
+    use std::os::unix::fs::MetadataExt;
+
+    group_sizes_by(path, |_, meta| meta.uid())
+}
+
+/// Get the total size of entries (in bytes), grouped by whether they are regular files, symlinks or other entries.
+///
+/// Unlike the other functions of this crate, symlinks count with their own size, like with
+/// [`SizeOptions::count_symlink_size`], and other entries with the length reported by their
+/// metadata, like with [`SizeOptions::fallback_to_len`], which is usually 0 for FIFOs,
+/// sockets and devices. Only the kinds found in the tree are present in the result.
+/// If `path` points to a directory, all of its files and subdirectories are included.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_size_by_file_kind(path: &Path) -> io::Result<HashMap<FileKind, u64>> {
+    let options = SizeOptions::new()
+        .count_symlink_size(true)
+        .fallback_to_len(true);
+    let kind_of = |_: &Path, meta: &Metadata| {
+        let file_type = meta.file_type();
+        if file_type.is_file() {
+            FileKind::File
+        } else if file_type.is_symlink() {
+            FileKind::Symlink
+        } else {
+            FileKind::Other
+        }
+    };
+    Ok(group_sizes_by_with(path, kind_of, &options)?)
+}
+
+/// Get the total size of files (in bytes), grouped by the key `key_fn` derives from each of them.
+///
+/// `key_fn` receives the path and metadata of every counted file, and may be called
+/// concurrently from several threads. If `path` points to a directory, all of its files
+/// and subdirectories are included. Memory grows with the number of distinct keys,
+/// not with the number of files.
+///
+/// ```no_run
+/// use dir_size::group_sizes_by;
+/// use std::path::Path;
+///
+/// // Total size of files by the first letter of their name.
+/// let sizes = group_sizes_by(Path::new("."), |path, _| {
+///     path.file_name()
+///         .and_then(|name| name.to_string_lossy().chars().next())
+/// })?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn group_sizes_by<K, F>(path: &Path, key_fn: F) -> io::Result<HashMap<K, u64>>
+where
+    K: Eq + Hash + Send,
+    F: Fn(&Path, &Metadata) -> K + Sync,
+{
+    Ok(group_sizes_by_with(path, key_fn, &SizeOptions::new())?)
+}
+
+/// Get the total size of files (in bytes), grouped by `key_fn` like [`group_sizes_by`], walking the tree according to `options`.
+///
+/// Sizes are measured as set by [`SizeOptions::size_kind`], and `key_fn` is only called on
+/// the files counted with the options.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn group_sizes_by_with<K, F>(
+    path: &Path,
+    key_fn: F,
+    options: &SizeOptions,
+) -> Result<HashMap<K, u64>, Error>
+where
+    K: Eq + Hash + Send,
+    F: Fn(&Path, &Metadata) -> K + Sync,
+{
+    Ok(Walker::new(options).run(path, &GroupBy { key_fn })?)
+}
+
+struct GroupBy<F> {
+    key_fn: F,
+}
+
+impl<K, F> Visitor for GroupBy<F>
+where
+    K: Eq + Hash + Send,
+    F: Fn(&Path, &Metadata) -> K + Sync,
+{
+    type Acc = HashMap<K, u64>;
+
+    fn init(&self) -> Self::Acc {
+        HashMap::new()
+    }
+
+    fn visit_file(&self, acc: &mut Self::Acc, path: &Path, meta: &Metadata, size: u64) {
+        *acc.entry((self.key_fn)(path, meta)).or_default() += size;
+    }
+
+    fn merge(&self, a: Self::Acc, b: Self::Acc) -> Self::Acc {
+        merge_maps(a, b)
+    }
+}
+
+fn extension_of(path: &Path, mode: ExtensionMode) -> String {
+    let Some(extension) = path.extension() else {
+        return String::new();
+    };
+    let extension = extension.to_string_lossy().to_lowercase();
+
+    if mode == ExtensionMode::Compound && COMPRESSION_EXTENSIONS.contains(&extension.as_str()) {
+        if let Some(inner) = path
+            .file_stem()
+            .and_then(|stem| Path::new(stem).extension())
+        {
+            return format!("{}.{extension}", inner.to_string_lossy().to_lowercase());
+        }
+    }
+    extension
+}
+
+/// Merge two maps by summing their values, always folding the smaller map into the larger one.
+fn merge_maps<K: Eq + Hash>(a: HashMap<K, u64>, b: HashMap<K, u64>) -> HashMap<K, u64> {
+    let (mut larger, smaller) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    for (key, size) in smaller {
+        *larger.entry(key).or_default() += size;
+    }
+    larger
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_get_size_by_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("a.txt"), [0; 10]).unwrap();
+        fs::write(dir.path().join("sub/b.txt"), [0; 20]).unwrap();
+        fs::write(dir.path().join("sub/c.rs"), [0; 30]).unwrap();
+        fs::write(dir.path().join("Makefile"), [0; 40]).unwrap();
+
+        let sizes = get_size_by_extension(dir.path()).unwrap();
+        assert_eq!(sizes.len(), 3);
+        assert_eq!(sizes["txt"], 30);
+        assert_eq!(sizes["rs"], 30);
+        assert_eq!(sizes[""], 40);
+    }
+
+    #[test]
+    fn test_extension_of() {
+        for (path, single, compound) in [
+            ("archive.tar.gz", "gz", "tar.gz"),
+            ("ARCHIVE.TAR.GZ", "gz", "tar.gz"),
+            ("notes.TXT", "txt", "txt"),
+            ("v1.2.txt", "txt", "txt"),
+            ("dir.d/Makefile", "", ""),
+            (".bashrc", "", ""),
+            (".bashrc.gz", "gz", "gz"),
+            ("plain.gz", "gz", "gz"),
+        ] {
+            let path = Path::new(path);
+            assert_eq!(extension_of(path, ExtensionMode::Single), single);
+            assert_eq!(extension_of(path, ExtensionMode::Compound), compound);
+        }
+    }
+
+    #[test]
+    fn test_get_size_by_compound_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.tar.gz"), [0; 10]).unwrap();
+        fs::write(dir.path().join("b.gz"), [0; 20]).unwrap();
+        fs::write(dir.path().join("c.TXT"), [0; 30]).unwrap();
+        fs::write(dir.path().join("d.txt"), [0; 40]).unwrap();
+        fs::write(dir.path().join("Makefile"), [0; 50]).unwrap();
+
+        assert_eq!(
+            get_size_by_extension(dir.path()).unwrap(),
+            HashMap::from([("gz".into(), 30), ("txt".into(), 70), ("".into(), 50)])
+        );
+        assert_eq!(
+            get_size_by_extension_with(dir.path(), ExtensionMode::Compound).unwrap(),
+            HashMap::from([
+                ("tar.gz".into(), 10),
+                ("gz".into(), 20),
+                ("txt".into(), 70),
+                ("".into(), 50)
+            ])
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_get_size_by_owner() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a"), [0; 10]).unwrap();
+        fs::write(dir.path().join("b"), [0; 20]).unwrap();
+
+        let uid = fs::metadata(dir.path()).unwrap().uid();
+        assert_eq!(
+            get_size_by_owner(dir.path()).unwrap(),
+            HashMap::from([(uid, 30)])
+        );
+    }
+
+    #[test]
+    fn test_get_size_by_file_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("a"), [0; 10]).unwrap();
+        fs::write(dir.path().join("sub/b"), [0; 20]).unwrap();
+
+        let sizes = get_size_by_file_kind(dir.path()).unwrap();
+        assert_eq!(sizes, HashMap::from([(FileKind::File, 30)]));
+
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("sub/link");
+            std::os::unix::fs::symlink("../a", &link).unwrap();
+            let _socket =
+                std::os::unix::net::UnixListener::bind(dir.path().join("socket")).unwrap();
+
+            let link_size = fs::symlink_metadata(&link).unwrap().len();
+            assert_eq!(
+                get_size_by_file_kind(dir.path()).unwrap(),
+                HashMap::from([
+                    (FileKind::File, 30),
+                    (FileKind::Symlink, link_size),
+                    (FileKind::Other, 0),
+                ])
+            );
+        }
+    }
+
+    #[test]
+    fn test_group_sizes_by() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("a"), [0; 10]).unwrap();
+        fs::write(dir.path().join("sub/b"), [0; 20]).unwrap();
+        fs::write(dir.path().join("sub/c"), [0; 30]).unwrap();
+
+        let sizes = group_sizes_by(dir.path(), |path, _| path.parent().unwrap().to_path_buf());
+        assert_eq!(
+            sizes.unwrap(),
+            HashMap::from([(dir.path().to_path_buf(), 10), (dir.path().join("sub"), 50)])
+        );
+        let sizes = group_sizes_by(dir.path(), |_, meta| meta.len() > 15);
+        assert_eq!(sizes.unwrap(), HashMap::from([(false, 10), (true, 50)]));
+    }
+
+    #[test]
+    fn test_merge_maps() {
+        let a = HashMap::from([("x", 1), ("y", 2)]);
+        let b = HashMap::from([("y", 3), ("z", 4), ("w", 5)]);
+        assert_eq!(
+            merge_maps(a, b),
+            HashMap::from([("x", 1), ("y", 5), ("z", 4), ("w", 5)])
+        );
+    }
+}
@@ -0,0 +1,153 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    walk::{Visitor, Walker},
+    Error, SizeOptions,
+};
+use std::{
+    cmp::Ordering,
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Get the number of files and their total size (in bytes) for every directory, densest first.
+///
+/// Returns `(directory, file_count, total_bytes)` for `path` itself and every directory below
+/// it. The counts are shallow: they only cover the files directly inside each directory, not
+/// those of its subdirectories, so a directory full of tiny files stands out even when nested
+/// in a large one. The result is sorted by descending density, i.e. files per byte, so that
+/// directories with many files but few bytes come first. Directories holding only empty files
+/// are the densest, and directories without files come last. Ties are sorted by descending
+/// file count, then by path.
+///
+/// Memory grows with the number of directories.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_dir_density(path: &Path) -> io::Result<Vec<(PathBuf, u64, u64)>> {
+    Ok(get_dir_density_with(path, &SizeOptions::new())?)
+}
+
+/// Get the file count and size (in bytes) of every directory like [`get_dir_density`], walking the tree according to `options`.
+///
+/// Sizes are measured as set by [`SizeOptions::size_kind`], and entries skipped by the
+/// options are neither counted nor returned.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_dir_density_with(
+    path: &Path,
+    options: &SizeOptions,
+) -> Result<Vec<(PathBuf, u64, u64)>, Error> {
+    let mut dirs = Walker::new(options).run(path, &Density)?.dirs;
+    dirs.sort_unstable_by(|(a_path, a_files, a_bytes), (b_path, b_files, b_bytes)| {
+        denser(*a_files, *a_bytes, *b_files, *b_bytes)
+            .then_with(|| b_files.cmp(a_files))
+            .then_with(|| a_path.cmp(b_path))
+    });
+    Ok(dirs)
+}
+
+/// Order two directories by descending density, with those without files last,
+/// so `Less` means that `a` is denser than `b`.
+fn denser(a_files: u64, a_bytes: u64, b_files: u64, b_bytes: u64) -> Ordering {
+    if a_files == 0 || b_files == 0 {
+        return b_files.min(1).cmp(&a_files.min(1));
+    }
+    // a_files / a_bytes > b_files / b_bytes, without dividing by zero or losing precision.
+    let a = u128::from(a_files) * u128::from(b_bytes);
+    let b = u128::from(b_files) * u128::from(a_bytes);
+    b.cmp(&a)
+}
+
+#[derive(Default)]
+struct Counts {
+    /// Files directly inside the directory being walked.
+    files: u64,
+    /// Bytes of the files directly inside the directory being walked.
+    bytes: u64,
+    /// Directories completed so far.
+    dirs: Vec<(PathBuf, u64, u64)>,
+}
+
+struct Density;
+
+impl Visitor for Density {
+    type Acc = Counts;
+
+    fn init(&self) -> Counts {
+        Counts::default()
+    }
+
+    fn visit_file(&self, acc: &mut Counts, _path: &Path, _meta: &Metadata, size: u64) {
+        acc.files += 1;
+        acc.bytes += size;
+    }
+
+    fn leave_dir(&self, acc: &mut Counts, path: &Path, _meta: &Metadata) {
+        // Reset the counts, so they do not add up to those of the parent.
+        let (files, bytes) = (
+            std::mem::take(&mut acc.files),
+            std::mem::take(&mut acc.bytes),
+        );
+        acc.dirs.push((path.to_path_buf(), files, bytes));
+    }
+
+    fn merge(&self, mut a: Counts, b: Counts) -> Counts {
+        a.files += b.files;
+        a.bytes += b.bytes;
+        a.dirs.extend(b.dirs);
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_get_dir_density() {
+        let dir = tempfile::tempdir().unwrap();
+        for sub in ["tiny", "large", "empty", "zero", "tiny/half"] {
+            fs::create_dir(dir.path().join(sub)).unwrap();
+        }
+        for (name, size) in [
+            ("root", 1000),
+            ("tiny/a", 1),
+            ("tiny/b", 1),
+            ("tiny/half/a", 2),
+            ("tiny/half/b", 2),
+            ("large/a", 10_000),
+            ("zero/a", 0),
+        ] {
+            fs::write(dir.path().join(name), vec![0; size]).unwrap();
+        }
+
+        assert_eq!(
+            get_dir_density(dir.path()).unwrap(),
+            [
+                (dir.path().join("zero"), 1, 0),
+                (dir.path().join("tiny"), 2, 2),
+                (dir.path().join("tiny/half"), 2, 4),
+                (dir.path().to_path_buf(), 1, 1000),
+                (dir.path().join("large"), 1, 10_000),
+                (dir.path().join("empty"), 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_denser() {
+        assert_eq!(denser(2, 1, 1, 1), Ordering::Less);
+        assert_eq!(denser(1, 10, 2, 2), Ordering::Greater);
+        assert_eq!(denser(2, 2, 1, 1), Ordering::Equal);
+        assert_eq!(denser(1, 0, 100, 1), Ordering::Less);
+        assert_eq!(denser(1, 0, 2, 0), Ordering::Equal);
+        assert_eq!(denser(0, 0, 1, u64::MAX), Ordering::Greater);
+        assert_eq!(denser(0, 0, 0, 0), Ordering::Equal);
+    }
+}
@@ -0,0 +1,174 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    walk::{Visitor, Walker},
+    Error, SizeOptions,
+};
+use std::{
+    collections::BTreeMap,
+    fs::Metadata,
+    path::{Path, PathBuf},
+};
+
+/// Collect every entry counted by a walk according to `options`, along with its metadata.
+///
+/// The result holds `path` itself, every directory walked below it, and every entry
+/// counting towards the size: files, symlinks if [`SizeOptions::count_symlink_size`] is set,
+/// and special files if [`SizeOptions::fallback_to_len`] is set. Entries skipped by the
+/// options (filtered files, directories not descended into, uncounted symlinks and special
+/// files) are left out, and so are inaccessible entries, which are handled according to the
+/// error policy; use [`ErrorPolicy::Callback`] to observe them, since there is nowhere to
+/// report them with [`ErrorPolicy::Collect`]. The entries are in no particular order.
+///
+/// Memory grows with the number of entries, so prefer the dedicated functions of this
+/// crate for large trees.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+///
+/// [`ErrorPolicy::Callback`]: crate::ErrorPolicy::Callback
+/// [`ErrorPolicy::Collect`]: crate::ErrorPolicy::Collect
+pub fn collect_entries(
+    path: &Path,
+    options: &SizeOptions,
+) -> Result<Vec<(PathBuf, Metadata)>, Error> {
+    Ok(Walker::new(options).run(path, &CollectEntries)?)
+}
+
+/// Map every entry counted by a walk according to `options` to its size (in bytes), sorted by path.
+///
+/// The map holds the same entries as [`collect_entries`] except directories, i.e. files and,
+/// depending on the options, symlinks or special files, each with the size it counts with,
+/// so the values add up to [`get_size_with_options`](crate::get_size_with_options). Unlike
+/// the parallel walk, iterating over the map always gives the same order, which suits
+/// golden tests, reproducible serialization, and diffing snapshots of a tree.
+///
+/// Memory grows with the number of files, since the whole map is built before returning.
+/// For large trees, prefer [`walk_dir_totals`](crate::walk_dir_totals), which streams the
+/// totals of directories in bounded memory.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn size_map(path: &Path, options: &SizeOptions) -> Result<BTreeMap<PathBuf, u64>, Error> {
+    Ok(Walker::new(options).run(path, &SizeMap)?)
+}
+
+struct CollectEntries;
+
+impl Visitor for CollectEntries {
+    type Acc = Vec<(PathBuf, Metadata)>;
+
+    fn init(&self) -> Self::Acc {
+        Vec::new()
+    }
+
+    fn visit_file(&self, acc: &mut Self::Acc, path: &Path, meta: &Metadata, _size: u64) {
+        acc.push((path.to_path_buf(), meta.clone()));
+    }
+
+    fn leave_dir(&self, acc: &mut Self::Acc, path: &Path, meta: &Metadata) {
+        acc.push((path.to_path_buf(), meta.clone()));
+    }
+
+    fn merge(&self, a: Self::Acc, b: Self::Acc) -> Self::Acc {
+        let (mut larger, smaller) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+        larger.extend(smaller);
+        larger
+    }
+}
+
+struct SizeMap;
+
+impl Visitor for SizeMap {
+    type Acc = BTreeMap<PathBuf, u64>;
+
+    fn init(&self) -> Self::Acc {
+        BTreeMap::new()
+    }
+
+    fn visit_file(&self, acc: &mut Self::Acc, path: &Path, _meta: &Metadata, size: u64) {
+        acc.insert(path.to_path_buf(), size);
+    }
+
+    fn merge(&self, a: Self::Acc, b: Self::Acc) -> Self::Acc {
+        let (mut larger, mut smaller) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+        larger.append(&mut smaller);
+        larger
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_collect_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::create_dir(dir.path().join("skip")).unwrap();
+        fs::write(dir.path().join("file"), [0; 100]).unwrap();
+        fs::write(dir.path().join("a/b/file"), [0; 200]).unwrap();
+        fs::write(dir.path().join("skip/file"), [0; 400]).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("file", dir.path().join("link")).unwrap();
+
+        let options = SizeOptions::new().descend_into(|path, _| !path.ends_with("skip"));
+        let mut entries = collect_entries(dir.path(), &options).unwrap();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let paths: Vec<_> = entries.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                dir.path().to_path_buf(),
+                dir.path().join("a"),
+                dir.path().join("a/b"),
+                dir.path().join("a/b/file"),
+                dir.path().join("file"),
+            ]
+        );
+        assert!(entries[1].1.is_dir());
+        assert_eq!(entries[3].1.len(), 200);
+    }
+
+    #[test]
+    fn test_size_map() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("z"), [0; 100]).unwrap();
+        fs::write(dir.path().join("a/b/file"), [0; 200]).unwrap();
+        fs::write(dir.path().join("a/file"), [0; 10]).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("0123456789", dir.path().join("link")).unwrap();
+
+        let map = size_map(dir.path(), &SizeOptions::new()).unwrap();
+        assert_eq!(
+            map.into_iter().collect::<Vec<_>>(),
+            [
+                (dir.path().join("a/b/file"), 200),
+                (dir.path().join("a/file"), 10),
+                (dir.path().join("z"), 100),
+            ]
+        );
+
+        let options = SizeOptions::new()
+            .count_symlink_size(true)
+            .descend_into(|path, _| !path.ends_with("b"));
+        let map = size_map(dir.path(), &options).unwrap();
+        let expected: BTreeMap<_, _> =
+            [(dir.path().join("a/file"), 10), (dir.path().join("z"), 100)]
+                .into_iter()
+                .chain(cfg!(unix).then(|| (dir.path().join("link"), 10)))
+                .collect();
+        assert_eq!(map, expected);
+        assert_eq!(
+            map.values().sum::<u64>(),
+            crate::get_size_with_options(dir.path(), &options)
+                .unwrap()
+                .bytes
+        );
+    }
+}
@@ -0,0 +1,101 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{error, fmt, io};
+
+/// The error type of the functions of this crate taking [`SizeOptions`], such as [`get_size_with_options`].
+///
+/// Every function taking options returns this type, and so do [`resume_size`],
+/// [`verify_against_manifest`] and `get_archive_uncompressed_size`. All other functions,
+/// such as [`get_size_in_bytes`] or [`get_usage`], the shorthand of [`get_usage_with`] with
+/// the default options, return [`io::Error`]. Converting this type into an [`io::Error`]
+/// carries it in an error of the closest kind, and converting that back with [`Error::from`]
+/// recovers the precise variant.
+///
+/// ```
+/// use dir_size::{get_size_with_options, Error, SizeOptions};
+/// use std::{io, path::Path};
+///
+/// let error = get_size_with_options(Path::new("missing"), &SizeOptions::new()).unwrap_err();
+/// assert!(matches!(error, Error::Io(e) if e.kind() == io::ErrorKind::NotFound));
+/// ```
+///
+/// [`SizeOptions`]: crate::SizeOptions
+/// [`get_size_with_options`]: crate::get_size_with_options
+/// [`resume_size`]: crate::resume_size
+/// [`verify_against_manifest`]: crate::verify_against_manifest
+/// [`get_size_in_bytes`]: crate::get_size_in_bytes
+/// [`get_usage`]: crate::get_usage
+/// [`get_usage_with`]: crate::get_usage_with
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An I/O error, e.g. on a path that does not exist or cannot be read.
+    Io(io::Error),
+    /// A size does not fit in 64 bits.
+    Overflow,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => e.fmt(f),
+            Self::Overflow => f.write_str("size is larger than 2^64 bytes"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Overflow => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    /// Recover the error carried by `e` if it comes from this crate, or wrap it in [`Error::Io`].
+    fn from(e: io::Error) -> Self {
+        if e.get_ref().is_some_and(|inner| inner.is::<Self>()) {
+            if let Ok(inner) = e.into_inner().unwrap().downcast::<Self>() {
+                return *inner;
+            }
+            unreachable!("the inner error was just checked to be an `Error`");
+        }
+        Self::Io(e)
+    }
+}
+
+impl From<Error> for io::Error {
+    /// Unwrap [`Error::Io`], or carry any other error in an [`io::Error`] of the closest kind.
+    fn from(e: Error) -> Self {
+        let kind = match e {
+            Error::Io(e) => return e,
+            Error::Overflow => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_io_round_trip() {
+        let io_error = io::Error::from(Error::Overflow);
+        assert_eq!(io_error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(io_error.to_string(), Error::Overflow.to_string());
+        assert!(matches!(Error::from(io_error), Error::Overflow));
+
+        let e = Error::from(io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(&e, Error::Io(e) if e.kind() == io::ErrorKind::NotFound));
+        assert!(e.source().is_some());
+        assert_eq!(io::Error::from(e).kind(), io::ErrorKind::NotFound);
+        let e = io::Error::other("not from this crate");
+        assert!(matches!(Error::from(e), Error::Io(_)));
+    }
+}
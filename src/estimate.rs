@@ -0,0 +1,252 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    hash::hash_path,
+    top::TopN,
+    walk::{normalize, walk, Visitor},
+};
+use rayon::prelude::*;
+use std::{
+    cmp::Reverse,
+    collections::hash_map::RandomState,
+    fs::{self, Metadata},
+    hash::{BuildHasher, Hasher},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Estimate the size of the file (in bytes) by only inspecting a random sample of files.
+///
+/// Works like [`estimate_size_seeded`] with a seed picked at random,
+/// so repeated calls may give different estimates. Runs in bounded memory.
+pub fn estimate_size(path: &Path, sample_fraction: f64) -> io::Result<u64> {
+    let seed = RandomState::new().build_hasher().finish();
+    estimate_size_seeded(path, sample_fraction, seed)
+}
+
+/// Estimate the size of the file (in bytes) by only inspecting a random sample of files.
+///
+/// The whole directory structure is still read, but only about `sample_fraction`
+/// of the files get their metadata fetched. The total is extrapolated as the
+/// average size of the sampled files times the number of files found.
+///
+/// Each file is picked independently with probability `sample_fraction`,
+/// based on a hash of its path and `seed`, so the same seed on an unchanged tree
+/// always gives the same estimate. The estimate assumes sampled files are
+/// representative of the rest: it is accurate when sizes are fairly uniform,
+/// but a few huge files dominating the total make it vary widely between seeds.
+/// If no file ends up in the sample, the estimate is 0.
+///
+/// A `sample_fraction` of `1.0` inspects every file and gives the exact size
+/// (the same as [`get_size_in_bytes`](crate::get_size_in_bytes)).
+///
+/// Runs in bounded memory.
+///
+/// This function will return an error if `sample_fraction` is not within `(0, 1]`,
+/// if `path` does not exist, or user lacks permissions to perform `metadata` call on `path`.
+pub fn estimate_size_seeded(path: &Path, sample_fraction: f64, seed: u64) -> io::Result<u64> {
+    if !(sample_fraction > 0.0 && sample_fraction <= 1.0) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "sample fraction must be within (0, 1]",
+        ));
+    }
+
+    let path = &normalize(path);
+    let meta = fs::symlink_metadata(path)?;
+    if !meta.is_dir() {
+        return Ok(if meta.is_file() { meta.len() } else { 0 });
+    }
+
+    let sampler = Sampler {
+        seed,
+        // Every hash below the threshold is sampled, the cast saturates at `u64::MAX`.
+        threshold: (sample_fraction * u64::MAX as f64) as u64,
+    };
+    let sample = sampler.sample_dir(path)?;
+
+    Ok(match sample.sampled_files {
+        0 => 0,
+        sampled_files => {
+            (u128::from(sample.sampled_bytes) * u128::from(sample.files)
+                / u128::from(sampled_files)) as u64
+        }
+    })
+}
+
+/// Pick `k` files uniformly at random, returning their paths and sizes (in bytes).
+///
+/// If `path` points to a directory, all of its files and the files of its subdirectories
+/// are candidates. If there are at most `k` files, all of them are returned.
+/// Every file gets a pseudo-random priority from a hash of its path and `seed`, and the `k`
+/// files of highest priority are kept, so the same seed on an unchanged tree always gives
+/// the same sample. The sample is sorted by path.
+///
+/// Only `k` files are tracked at any time, so memory usage does not grow with the tree.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn sample_files(path: &Path, k: usize, seed: u64) -> io::Result<Vec<(PathBuf, u64)>> {
+    let mut sample: Vec<_> = walk(path, &SampleFiles { k, seed })?
+        .into_sorted_vec()
+        .into_iter()
+        .map(|(_, Reverse(path), size)| (path, size))
+        .collect();
+    sample.sort_unstable();
+    Ok(sample)
+}
+
+struct SampleFiles {
+    k: usize,
+    seed: u64,
+}
+
+impl Visitor for SampleFiles {
+    type Acc = TopN<(u64, Reverse<PathBuf>, u64)>;
+
+    fn init(&self) -> Self::Acc {
+        TopN::new(self.k)
+    }
+
+    fn visit_file(&self, acc: &mut Self::Acc, path: &Path, _meta: &Metadata, size: u64) {
+        let priority = hash_path(self.seed, path);
+        if acc
+            .threshold()
+            .is_none_or(|(threshold, _, _)| priority >= *threshold)
+        {
+            acc.push((priority, Reverse(path.to_path_buf()), size));
+        }
+    }
+
+    fn merge(&self, a: Self::Acc, b: Self::Acc) -> Self::Acc {
+        a.merge(b)
+    }
+}
+
+struct Sampler {
+    seed: u64,
+    threshold: u64,
+}
+
+#[derive(Default)]
+struct Sample {
+    files: u64,
+    sampled_files: u64,
+    sampled_bytes: u64,
+}
+
+impl Sample {
+    fn merge(self, other: Self) -> Self {
+        Self {
+            files: self.files + other.files,
+            sampled_files: self.sampled_files + other.sampled_files,
+            sampled_bytes: self.sampled_bytes + other.sampled_bytes,
+        }
+    }
+}
+
+impl Sampler {
+    fn sample_dir(&self, path: &Path) -> io::Result<Sample> {
+        let entries: Vec<_> = fs::read_dir(path)?.collect();
+
+        Ok(entries
+            .par_iter()
+            .filter_map(|entry| entry.as_ref().ok())
+            .fold(Sample::default, |mut sample, entry| {
+                // `file_type` is usually answered by `read_dir` itself without an extra `stat`.
+                match entry.file_type() {
+                    Ok(file_type) if file_type.is_file() => {
+                        sample.files += 1;
+                        let entry_path = entry.path();
+                        if hash_path(self.seed, &entry_path) <= self.threshold {
+                            if let Ok(meta) = entry.metadata() {
+                                sample.sampled_files += 1;
+                                sample.sampled_bytes += meta.len();
+                            }
+                        }
+                    }
+                    Ok(file_type) if file_type.is_dir() => {
+                        if let Ok(sub) = self.sample_dir(&entry.path()) {
+                            sample = sample.merge(sub);
+                        }
+                    }
+                    _ => {}
+                }
+                sample
+            })
+            .reduce(Sample::default, Sample::merge))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniform_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for sub in 0..10 {
+            let sub = dir.path().join(sub.to_string());
+            fs::create_dir(&sub).unwrap();
+            for file in 0..100 {
+                fs::write(sub.join(file.to_string()), [0; 10]).unwrap();
+            }
+        }
+        dir
+    }
+
+    #[test]
+    fn test_estimate_size_full_sample_is_exact() {
+        let dir = uniform_tree();
+        fs::write(dir.path().join("big"), [0; 12345]).unwrap();
+        assert_eq!(estimate_size(dir.path(), 1.0).unwrap(), 22345);
+    }
+
+    #[test]
+    fn test_estimate_size_uniform_tree() {
+        let dir = uniform_tree();
+        for seed in 0..10 {
+            assert_eq!(estimate_size_seeded(dir.path(), 0.1, seed).unwrap(), 10000);
+        }
+    }
+
+    #[test]
+    fn test_estimate_size_is_reproducible() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in 0..1000 {
+            fs::write(dir.path().join(file.to_string()), vec![0; file]).unwrap();
+        }
+        let estimate = estimate_size_seeded(dir.path(), 0.05, 42).unwrap();
+        assert_eq!(
+            estimate_size_seeded(dir.path(), 0.05, 42).unwrap(),
+            estimate
+        );
+    }
+
+    #[test]
+    fn test_sample_files() {
+        let dir = uniform_tree();
+        let sample = sample_files(dir.path(), 5, 7).unwrap();
+        assert_eq!(sample.len(), 5);
+        assert!(sample.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(sample.iter().all(|(_, size)| *size == 10));
+        assert_eq!(sample_files(dir.path(), 5, 7).unwrap(), sample);
+        assert_ne!(sample_files(dir.path(), 5, 8).unwrap(), sample);
+
+        assert_eq!(sample_files(dir.path(), 2000, 7).unwrap().len(), 1000);
+    }
+
+    #[test]
+    fn test_estimate_size_invalid_fraction() {
+        let dir = tempfile::tempdir().unwrap();
+        for sample_fraction in [0.0, -0.5, 1.5, f64::NAN] {
+            assert_eq!(
+                estimate_size(dir.path(), sample_fraction)
+                    .unwrap_err()
+                    .kind(),
+                io::ErrorKind::InvalidInput
+            );
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{
+    fs::{File, Metadata},
+    io,
+    os::{
+        fd::AsRawFd,
+        unix::fs::{FileTypeExt, OpenOptionsExt},
+    },
+    path::Path,
+};
+
+/// Whether `meta` is the metadata of a named pipe.
+pub(crate) fn is_fifo(meta: &Metadata) -> bool {
+    meta.file_type().is_fifo()
+}
+
+/// The number of bytes currently buffered in the named pipe at `path`, waiting to be read.
+///
+/// The pipe is opened for reading without blocking and without reading anything,
+/// so the buffered data is left for its actual reader.
+pub(crate) fn pending_bytes(path: &Path) -> io::Result<u64> {
+    let file = File::options()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)?;
+    let mut pending: libc::c_int = 0;
+    // SAFETY: `FIONREAD` writes the number of readable bytes to the `c_int` it is given.
+    if unsafe { libc::ioctl(file.as_raw_fd(), libc::FIONREAD, &mut pending) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(pending.try_into().unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_size_with_options, SizeOptions};
+    use std::{ffi::CString, fs, io::Write, os::unix::ffi::OsStrExt};
+
+    #[test]
+    fn test_pending_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let fifo = dir.path().join("fifo");
+        let c_path = CString::new(fifo.as_os_str().as_bytes()).unwrap();
+        // SAFETY: `c_path` is a valid NUL-terminated path.
+        assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+        fs::write(dir.path().join("file"), [0; 100]).unwrap();
+
+        // Keep a reader open, so that the writer can be opened and its data stays buffered.
+        let _reader = File::options()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&fifo)
+            .unwrap();
+        let mut writer = File::options().write(true).open(&fifo).unwrap();
+        writer.write_all(&[0; 42]).unwrap();
+
+        assert!(is_fifo(&fs::symlink_metadata(&fifo).unwrap()));
+        assert_eq!(pending_bytes(&fifo).unwrap(), 42);
+
+        let size = |count_fifo_buffers| {
+            let options = SizeOptions::new().count_fifo_buffers(count_fifo_buffers);
+            get_size_with_options(dir.path(), &options).unwrap().bytes
+        };
+        assert_eq!(size(false), 100);
+        assert_eq!(size(true), 142);
+        // Measuring does not consume the buffered data.
+        assert_eq!(pending_bytes(&fifo).unwrap(), 42);
+    }
+}
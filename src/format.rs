@@ -0,0 +1,710 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::get_size_in_bytes;
+use rayon::prelude::*;
+use std::{io, path::Path};
+
+/// Decimals beyond this are dropped, as they can never be non-zero for a `u64` size.
+const MAX_DECIMALS: usize = 19;
+
+/// The standard defining the size and the name of the units sizes are displayed in.
+///
+/// The three standards disagree on what "kilobyte" means, so the standard decides both
+/// the factor between units and their spelling, keeping every formatted size unambiguous.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnitStandard {
+    /// IEC binary prefixes: 1 KiB is 1024 bytes. This is the default.
+    #[default]
+    Iec,
+    /// JEDEC binary prefixes: 1 KB is 1024 bytes, as commonly used for memory.
+    Jedec,
+    /// SI decimal prefixes: 1 kB is 1000 bytes, as commonly used for storage devices.
+    Si,
+}
+
+impl UnitStandard {
+    /// The factor between two successive units.
+    pub fn base(self) -> u64 {
+        match self {
+            Self::Iec | Self::Jedec => 1024,
+            Self::Si => 1000,
+        }
+    }
+}
+
+/// A unit to display sizes in, using binary prefixes (powers of 1024) unless
+/// a [`UnitStandard`] says otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Unit {
+    /// Bytes.
+    Bytes,
+    /// Kibibytes (KiB), 1024 bytes.
+    Kilo,
+    /// Mebibytes (MiB), 1024 KiB.
+    Mega,
+    /// Gibibytes (GiB), 1024 MiB.
+    Giga,
+    /// Tebibytes (TiB), 1024 GiB.
+    Tera,
+    /// Pebibytes (PiB), 1024 TiB.
+    Peta,
+    /// Exbibytes (EiB), 1024 PiB.
+    Exa,
+}
+
+impl Unit {
+    const ALL: [Self; 7] = [
+        Self::Bytes,
+        Self::Kilo,
+        Self::Mega,
+        Self::Giga,
+        Self::Tera,
+        Self::Peta,
+        Self::Exa,
+    ];
+
+    /// The largest unit of `standard` in which `size` is at least 1, as picked by
+    /// [`get_size_in_human_bytes_with`](crate::get_size_in_human_bytes_with).
+    pub fn for_size_in(size: u64, standard: UnitStandard) -> Self {
+        Self::ALL
+            .into_iter()
+            .rev()
+            .find(|unit| size >= unit.bytes_in(standard))
+            .unwrap_or(Self::Bytes)
+    }
+
+    /// The number of bytes in one of this unit, as defined by `standard`.
+    pub fn bytes_in(self, standard: UnitStandard) -> u64 {
+        standard.base().pow(self as u32)
+    }
+
+    fn name(self, standard: UnitStandard) -> &'static str {
+        let names = match standard {
+            UnitStandard::Iec => ["Bytes", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+            UnitStandard::Jedec => ["Bytes", "KB", "MB", "GB", "TB", "PB", "EB"],
+            UnitStandard::Si => ["Bytes", "kB", "MB", "GB", "TB", "PB", "EB"],
+        };
+        names[self as usize]
+    }
+
+    fn abbr(self, standard: UnitStandard) -> &'static str {
+        match (self, standard) {
+            (Self::Kilo, UnitStandard::Si) => "k",
+            _ => ["B", "K", "M", "G", "T", "P", "E"][self as usize],
+        }
+    }
+}
+
+/// Format `size` (in bytes) in `unit`, rounded to `decimals` decimal places.
+///
+/// Works like [`format_in_unit_with`] using [`UnitStandard::Iec`].
+///
+/// ```
+/// use dir_size::{format_in_unit, Unit};
+///
+/// assert_eq!(format_in_unit(1536, Unit::Kilo, 2), "1.50 KiB");
+/// assert_eq!(format_in_unit(1536, Unit::Mega, 3), "0.001 MiB");
+/// assert_eq!(format_in_unit(1536, Unit::Bytes, 2), "1536 Bytes");
+/// ```
+pub fn format_in_unit(size: u64, unit: Unit, decimals: usize) -> String {
+    format_in_unit_with(size, unit, decimals, UnitStandard::Iec)
+}
+
+/// Format `size` (in bytes) in `unit` as defined by `standard`, rounded to `decimals` decimal places.
+///
+/// Sizes in [`Unit::Bytes`] are always exact and shown without decimals.
+///
+/// ```
+/// use dir_size::{format_in_unit_with, Unit, UnitStandard};
+///
+/// assert_eq!(format_in_unit_with(1500, Unit::Kilo, 1, UnitStandard::Iec), "1.5 KiB");
+/// assert_eq!(format_in_unit_with(1500, Unit::Kilo, 1, UnitStandard::Jedec), "1.5 KB");
+/// assert_eq!(format_in_unit_with(1500, Unit::Kilo, 1, UnitStandard::Si), "1.5 kB");
+/// ```
+pub fn format_in_unit_with(
+    size: u64,
+    unit: Unit,
+    decimals: usize,
+    standard: UnitStandard,
+) -> String {
+    let style = FormatStyle::new()
+        .standard(standard)
+        .unit(unit)
+        .decimals(decimals)
+        .rounding(Rounding::HalfUp);
+    format_size(size, &style)
+}
+
+/// How a size is rounded to the decimals it is displayed with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rounding {
+    /// Drop the remaining digits, so a size is never displayed as larger than it is.
+    /// This is the default.
+    #[default]
+    Truncate,
+    /// Round to the nearest value, and halfway values up.
+    HalfUp,
+}
+
+/// How a size is padded to the width it is displayed in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Alignment {
+    /// Pad on the right.
+    Left,
+    /// Pad on the left, so that units line up in a column. This is the default.
+    #[default]
+    Right,
+}
+
+/// Every option controlling how [`format_size`] displays a size.
+///
+/// The default style is the one of [`get_size_in_human_bytes`](crate::get_size_in_human_bytes):
+/// IEC units, the largest unit in which the size is at least 1, no decimals and no padding.
+///
+/// ```
+/// use dir_size::{format_size, FormatStyle, Rounding, Unit};
+///
+/// let style = FormatStyle::new()
+///     .unit(Unit::Kilo)
+///     .decimals(2)
+///     .rounding(Rounding::HalfUp)
+///     .thousands_separator(Some(','))
+///     .width(14);
+/// assert_eq!(format_size(5_000_000, &style), "  4,882.81 KiB");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FormatStyle {
+    standard: UnitStandard,
+    unit: Option<Unit>,
+    decimals: usize,
+    rounding: Rounding,
+    thousands_separator: Option<char>,
+    decimal_separator: char,
+    abbreviate: bool,
+    width: usize,
+    alignment: Alignment,
+}
+
+impl Default for FormatStyle {
+    fn default() -> Self {
+        Self {
+            standard: UnitStandard::default(),
+            unit: None,
+            decimals: 0,
+            rounding: Rounding::default(),
+            thousands_separator: None,
+            decimal_separator: '.',
+            abbreviate: false,
+            width: 0,
+            alignment: Alignment::default(),
+        }
+    }
+}
+
+impl FormatStyle {
+    /// Create the default style.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the standard defining the units (default: [`UnitStandard::Iec`]).
+    pub fn standard(mut self, standard: UnitStandard) -> Self {
+        self.standard = standard;
+        self
+    }
+
+    /// Always display sizes in `unit` (default: the largest unit in which the size is at least 1,
+    /// as picked by [`Unit::for_size_in`]).
+    pub fn unit(mut self, unit: Unit) -> Self {
+        self.unit = Some(unit);
+        self
+    }
+
+    /// Set the number of decimal places (default: 0).
+    ///
+    /// Sizes in [`Unit::Bytes`] are always exact and shown without decimals.
+    /// Decimals beyond the 19th can never be non-zero for a `u64` size, so they are dropped.
+    pub fn decimals(mut self, decimals: usize) -> Self {
+        self.decimals = decimals.min(MAX_DECIMALS);
+        self
+    }
+
+    /// Set how sizes are rounded to the decimals (default: [`Rounding::Truncate`]).
+    ///
+    /// With [`Rounding::HalfUp`] and an automatically picked unit, a size just below
+    /// the next unit may be displayed as e.g. `1024 KiB`.
+    pub fn rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Set the separator inserted between groups of three digits (default: none).
+    pub fn thousands_separator(mut self, separator: Option<char>) -> Self {
+        self.thousands_separator = separator;
+        self
+    }
+
+    /// Set the separator between the integer and the decimals (default: `.`).
+    pub fn decimal_separator(mut self, separator: char) -> Self {
+        self.decimal_separator = separator;
+        self
+    }
+
+    /// Set whether units are abbreviated to a single letter, e.g. `K` for `KiB` (default: `false`).
+    pub fn abbreviate(mut self, abbreviate: bool) -> Self {
+        self.abbreviate = abbreviate;
+        self
+    }
+
+    /// Pad the formatted size to at least `width` characters (default: 0, no padding).
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Set on which side the formatted size is padded to the width (default: [`Alignment::Right`]).
+    pub fn alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+}
+
+/// Format `size` (in bytes) according to every option of `style`.
+///
+/// This is the formatter behind every other formatting function of this crate.
+/// The integer part and the decimals are computed in integers, so large sizes keep every digit.
+///
+/// ```
+/// use dir_size::{format_size, FormatStyle, UnitStandard};
+///
+/// assert_eq!(format_size(1, &FormatStyle::new()), "1 Byte");
+/// assert_eq!(format_size(1536, &FormatStyle::new().decimals(1)), "1.5 KiB");
+/// assert_eq!(format_size(1536, &FormatStyle::new().abbreviate(true)), "1 K");
+/// assert_eq!(format_size(1536, &FormatStyle::new().standard(UnitStandard::Si)), "1 kB");
+/// ```
+pub fn format_size(size: u64, style: &FormatStyle) -> String {
+    let standard = style.standard;
+    let unit = style
+        .unit
+        .unwrap_or_else(|| Unit::for_size_in(size, standard));
+
+    let mut formatted = if unit == Unit::Bytes {
+        group_digits(&size.to_string(), style.thousands_separator)
+    } else {
+        let decimals = style.decimals;
+        let scale = 10u128.pow(decimals as u32);
+        let divisor = u128::from(unit.bytes_in(standard));
+        let rounding = match style.rounding {
+            Rounding::Truncate => 0,
+            Rounding::HalfUp => divisor / 2,
+        };
+        let scaled = (u128::from(size) * scale + rounding) / divisor;
+        let (integer, fraction) = (scaled / scale, scaled % scale);
+
+        let mut formatted = group_digits(&integer.to_string(), style.thousands_separator);
+        if decimals > 0 {
+            formatted.push(style.decimal_separator);
+            formatted.push_str(&format!("{fraction:0decimals$}"));
+        }
+        formatted
+    };
+
+    formatted.push(' ');
+    formatted.push_str(if style.abbreviate {
+        unit.abbr(standard)
+    } else if unit == Unit::Bytes && size == 1 {
+        "Byte"
+    } else {
+        unit.name(standard)
+    });
+    pad(&formatted, style)
+}
+
+/// Format `size` (in bytes) according to `style`, followed by the exact number of bytes.
+///
+/// This is the form often used in reports, e.g. `1.50 GiB (1610612736 bytes)`. The exact
+/// number of bytes uses the thousands separator of `style`, and the width of `style` applies
+/// to the whole result. When the size is displayed in [`Unit::Bytes`], it is already exact,
+/// so the redundant number in parentheses is left out.
+///
+/// ```
+/// use dir_size::{format_size_verbose, FormatStyle};
+///
+/// let style = FormatStyle::new().decimals(2);
+/// assert_eq!(format_size_verbose(1610612736, &style), "1.50 GiB (1610612736 bytes)");
+/// assert_eq!(format_size_verbose(512, &style), "512 Bytes");
+/// ```
+pub fn format_size_verbose(size: u64, style: &FormatStyle) -> String {
+    let mut formatted = format_size(size, &style.clone().width(0));
+    let unit = style
+        .unit
+        .unwrap_or_else(|| Unit::for_size_in(size, style.standard));
+    if unit != Unit::Bytes {
+        let bytes = group_digits(&size.to_string(), style.thousands_separator);
+        let name = if size == 1 { "byte" } else { "bytes" };
+        formatted.push_str(&format!(" ({bytes} {name})"));
+    }
+    pad(&formatted, style)
+}
+
+/// Pad `formatted` to the width of `style`.
+fn pad(formatted: &str, style: &FormatStyle) -> String {
+    let width = style.width;
+    match style.alignment {
+        Alignment::Left => format!("{formatted:<width$}"),
+        Alignment::Right => format!("{formatted:>width$}"),
+    }
+}
+
+/// Insert `separator` between every group of three digits of `digits`.
+fn group_digits(digits: &str, separator: Option<char>) -> String {
+    let Some(separator) = separator else {
+        return digits.to_string();
+    };
+    let mut grouped = String::with_capacity(digits.len() * 2);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Get the sizes of several files (in bytes), formatted in a common unit so they line up in a table.
+///
+/// The sizes are calculated in parallel, each like [`get_size_in_bytes`] does,
+/// and returned in the same order as `paths`, along with their formatted form.
+/// All of them are formatted with [`format_size`] according to `style`, in the unit of
+/// `style` if set, or else in the unit of its standard that [`Unit::for_size_in`] picks
+/// for the largest successfully calculated size. Setting a width on `style` lines up
+/// the formatted sizes in a column.
+///
+/// Memory grows with the number of paths.
+pub fn get_sizes_in_common_unit<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    style: &FormatStyle,
+) -> Vec<io::Result<(u64, String)>> {
+    let sizes: Vec<_> = paths
+        .par_iter()
+        .map(|path| get_size_in_bytes(path.as_ref()))
+        .collect();
+    let unit = style.unit.unwrap_or_else(|| {
+        let largest = sizes.iter().flatten().copied().max().unwrap_or(0);
+        Unit::for_size_in(largest, style.standard)
+    });
+    let style = style.clone().unit(unit);
+
+    sizes
+        .into_iter()
+        .map(|size| size.map(|size| (size, format_size(size, &style))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const KIBIBYTE: u64 = 1 << 10;
+    const MEBIBYTE: u64 = 1 << 20;
+    const GIBIBYTE: u64 = 1 << 30;
+    const TEBIBYTE: u64 = 1 << 40;
+    const PEBIBYTE: u64 = 1 << 50;
+    const EXBIBYTE: u64 = 1 << 60;
+
+    #[test]
+    fn test_format_size_human() {
+        for (size_in_bytes, human_bytes) in [
+            (0, "0 Bytes"),
+            (1, "1 Byte"),
+            (2, "2 Bytes"),
+            (KIBIBYTE - 1, "1023 Bytes"),
+            (KIBIBYTE, "1 KiB"),
+            (MEBIBYTE - 1, "1023 KiB"),
+            (MEBIBYTE, "1 MiB"),
+            (GIBIBYTE - 1, "1023 MiB"),
+            (GIBIBYTE, "1 GiB"),
+            (TEBIBYTE - 1, "1023 GiB"),
+            (TEBIBYTE, "1 TiB"),
+            (PEBIBYTE - 1, "1023 TiB"),
+            (PEBIBYTE, "1 PiB"),
+            (EXBIBYTE - 1, "1023 PiB"),
+            (EXBIBYTE, "1 EiB"),
+        ] {
+            println!("{size_in_bytes} bytes -> {human_bytes}");
+            assert_eq!(format_size(size_in_bytes, &FormatStyle::new()), human_bytes);
+        }
+    }
+
+    #[test]
+    fn test_format_size_abbr() {
+        for (size_in_bytes, abbr_human_bytes) in [
+            (0, "0 B"),
+            (1, "1 B"),
+            (KIBIBYTE - 1, "1023 B"),
+            (KIBIBYTE, "1 K"),
+            (MEBIBYTE - 1, "1023 K"),
+            (MEBIBYTE, "1 M"),
+            (GIBIBYTE - 1, "1023 M"),
+            (GIBIBYTE, "1 G"),
+            (TEBIBYTE - 1, "1023 G"),
+            (TEBIBYTE, "1 T"),
+            (PEBIBYTE - 1, "1023 T"),
+            (PEBIBYTE, "1 P"),
+            (EXBIBYTE - 1, "1023 P"),
+            (EXBIBYTE, "1 E"),
+        ] {
+            println!("{size_in_bytes} bytes -> {abbr_human_bytes}");
+            assert_eq!(
+                format_size(size_in_bytes, &FormatStyle::new().abbreviate(true)),
+                abbr_human_bytes
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_size() {
+        let style = FormatStyle::new;
+        for (size, style, formatted) in [
+            (MEBIBYTE - 1, style(), "1023 KiB"),
+            (MEBIBYTE - 1, style().rounding(Rounding::HalfUp), "1024 KiB"),
+            (1535, style().decimals(2), "1.49 KiB"),
+            (
+                1535,
+                style().decimals(2).rounding(Rounding::HalfUp),
+                "1.50 KiB",
+            ),
+            (
+                1_234_567,
+                style().unit(Unit::Bytes).decimals(2),
+                "1234567 Bytes",
+            ),
+            (
+                1_234_567,
+                style().unit(Unit::Bytes).thousands_separator(Some(',')),
+                "1,234,567 Bytes",
+            ),
+            (
+                5 * GIBIBYTE + GIBIBYTE / 2,
+                style()
+                    .unit(Unit::Mega)
+                    .decimals(1)
+                    .thousands_separator(Some('.'))
+                    .decimal_separator(','),
+                "5.632,0 MiB",
+            ),
+            (
+                1_500_000,
+                style()
+                    .standard(UnitStandard::Si)
+                    .decimals(1)
+                    .abbreviate(true),
+                "1.5 M",
+            ),
+            (1, style().abbreviate(true).width(6), "   1 B"),
+            (1, style().width(8).alignment(Alignment::Left), "1 Byte  "),
+            (
+                1536,
+                style()
+                    .standard(UnitStandard::Jedec)
+                    .decimals(1)
+                    .width(10)
+                    .alignment(Alignment::Right),
+                "    1.5 KB",
+            ),
+            (1_234_567, style().width(3), "1 MiB"),
+            (
+                u64::MAX,
+                style().unit(Unit::Kilo).thousands_separator(Some('_')),
+                "18_014_398_509_481_983 KiB",
+            ),
+            (
+                KIBIBYTE + 1,
+                style().unit(Unit::Kilo).decimals(30),
+                "1.0009765625000000000 KiB",
+            ),
+        ] {
+            assert_eq!(format_size(size, &style), formatted, "{style:?}");
+        }
+    }
+
+    #[test]
+    fn test_format_size_verbose() {
+        let style = FormatStyle::new;
+        for (size, style, formatted) in [
+            (512, style(), "512 Bytes"),
+            (1, style(), "1 Byte"),
+            (0, style().unit(Unit::Bytes), "0 Bytes"),
+            (
+                3 * GIBIBYTE / 2,
+                style().decimals(2),
+                "1.50 GiB (1610612736 bytes)",
+            ),
+            (
+                3 * GIBIBYTE / 2,
+                style().thousands_separator(Some(',')).abbreviate(true),
+                "1 G (1,610,612,736 bytes)",
+            ),
+            (1, style().unit(Unit::Kilo).decimals(1), "0.0 KiB (1 byte)"),
+            (
+                1500,
+                style().standard(UnitStandard::Si).decimals(1).width(23),
+                "    1.5 kB (1500 bytes)",
+            ),
+            (
+                KIBIBYTE,
+                style().width(19).alignment(Alignment::Left),
+                "1 KiB (1024 bytes) ",
+            ),
+        ] {
+            assert_eq!(format_size_verbose(size, &style), formatted, "{style:?}");
+        }
+    }
+
+    #[test]
+    fn test_group_digits() {
+        for (digits, grouped) in [
+            ("0", "0"),
+            ("123", "123"),
+            ("1234", "1,234"),
+            ("123456", "123,456"),
+            ("1234567", "1,234,567"),
+        ] {
+            assert_eq!(group_digits(digits, Some(',')), grouped);
+            assert_eq!(group_digits(digits, None), digits);
+        }
+    }
+
+    #[test]
+    fn test_unit_standards() {
+        for (size, iec, jedec, si) in [
+            (999, "999 Bytes", "999 Bytes", "999 Bytes"),
+            (1000, "1000 Bytes", "1000 Bytes", "1 kB"),
+            (1023, "1023 Bytes", "1023 Bytes", "1 kB"),
+            (1024, "1 KiB", "1 KB", "1 kB"),
+            (1025, "1 KiB", "1 KB", "1 kB"),
+            (1024 * 1024, "1 MiB", "1 MB", "1 MB"),
+            (u64::MAX, "15 EiB", "15 EB", "18 EB"),
+        ] {
+            for (standard, expected) in [
+                (UnitStandard::Iec, iec),
+                (UnitStandard::Jedec, jedec),
+                (UnitStandard::Si, si),
+            ] {
+                assert_eq!(
+                    format_size(size, &FormatStyle::new().standard(standard)),
+                    expected
+                );
+            }
+        }
+        assert_eq!(
+            format_size(
+                1024,
+                &FormatStyle::new()
+                    .standard(UnitStandard::Jedec)
+                    .abbreviate(true)
+            ),
+            "1 K"
+        );
+        assert_eq!(
+            format_size(
+                1024,
+                &FormatStyle::new()
+                    .standard(UnitStandard::Si)
+                    .abbreviate(true)
+            ),
+            "1 k"
+        );
+
+        for (standard, formatted) in [
+            (UnitStandard::Iec, "1.000 KiB"),
+            (UnitStandard::Jedec, "1.000 KB"),
+            (UnitStandard::Si, "1.024 kB"),
+        ] {
+            assert_eq!(
+                format_in_unit_with(1024, Unit::Kilo, 3, standard),
+                formatted
+            );
+        }
+        assert_eq!(Unit::for_size_in(1023, UnitStandard::Si), Unit::Kilo);
+        assert_eq!(Unit::for_size_in(1023, UnitStandard::Jedec), Unit::Bytes);
+        assert_eq!(Unit::Exa.bytes_in(UnitStandard::Si), 10u64.pow(18));
+    }
+
+    #[test]
+    fn test_unit_for_size() {
+        for (size, unit) in [
+            (0, Unit::Bytes),
+            (KIBIBYTE - 1, Unit::Bytes),
+            (KIBIBYTE, Unit::Kilo),
+            (MEBIBYTE, Unit::Mega),
+            (GIBIBYTE - 1, Unit::Mega),
+            (EXBIBYTE, Unit::Exa),
+            (u64::MAX, Unit::Exa),
+        ] {
+            assert_eq!(Unit::for_size_in(size, UnitStandard::Iec), unit);
+        }
+    }
+
+    #[test]
+    fn test_format_in_unit() {
+        for (size, unit, decimals, formatted) in [
+            (0, Unit::Kilo, 2, "0.00 KiB"),
+            (1, Unit::Bytes, 2, "1 Byte"),
+            (512, Unit::Bytes, 2, "512 Bytes"),
+            (512, Unit::Kilo, 1, "0.5 KiB"),
+            (1536, Unit::Kilo, 0, "2 KiB"),
+            (5 * GIBIBYTE, Unit::Mega, 0, "5120 MiB"),
+            (900 * MEBIBYTE, Unit::Giga, 2, "0.88 GiB"),
+            (MEBIBYTE - 1, Unit::Kilo, 2, "1024.00 KiB"),
+            (u64::MAX, Unit::Exa, 3, "16.000 EiB"),
+            (u64::MAX, Unit::Bytes, 0, "18446744073709551615 Bytes"),
+            (KIBIBYTE + 1, Unit::Kilo, 30, "1.0009765625000000000 KiB"),
+        ] {
+            assert_eq!(format_in_unit(size, unit, decimals), formatted);
+        }
+    }
+
+    #[test]
+    fn test_get_sizes_in_common_unit() {
+        let dir = tempfile::tempdir().unwrap();
+        let small = dir.path().join("small");
+        let large = dir.path().join("large");
+        let missing = dir.path().join("missing");
+        fs::write(&small, vec![0; 512]).unwrap();
+        fs::write(&large, vec![0; 3 * KIBIBYTE as usize]).unwrap();
+
+        let formatted = |sizes: Vec<io::Result<(u64, String)>>| -> Vec<_> {
+            sizes
+                .into_iter()
+                .map(|size| size.ok().map(|(_, formatted)| formatted))
+                .collect()
+        };
+        let paths = [&small, &large, &missing];
+        let sizes = get_sizes_in_common_unit(&paths, &FormatStyle::new().decimals(1));
+        assert_eq!(sizes[0].as_ref().unwrap().0, 512);
+        assert_eq!(sizes[1].as_ref().unwrap().0, 3072);
+        assert_eq!(
+            formatted(sizes),
+            [Some("0.5 KiB".into()), Some("3.0 KiB".into()), None]
+        );
+
+        let style = FormatStyle::new()
+            .standard(UnitStandard::Si)
+            .decimals(1)
+            .rounding(Rounding::HalfUp)
+            .width(8);
+        assert_eq!(
+            formatted(get_sizes_in_common_unit(&paths, &style)),
+            [Some("  0.5 kB".into()), Some("  3.1 kB".into()), None]
+        );
+        let style = FormatStyle::new().unit(Unit::Bytes);
+        assert_eq!(
+            formatted(get_sizes_in_common_unit(&[&small], &style)),
+            [Some("512 Bytes".into())]
+        );
+    }
+}
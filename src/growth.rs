@@ -0,0 +1,124 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::time::{Duration, SystemTime};
+
+/// Get how fast a size grew (in bytes per second) between two timestamped measurements.
+///
+/// `prev` and `curr` are each the time a size was measured at and the size (in bytes),
+/// e.g. the start time and result of two scans of the same tree. The rate is negative if
+/// the size shrank, and `0.0` if no time elapsed between the measurements, since no rate
+/// can be derived from them. Measurements passed in reverse order give the same rate.
+///
+/// ```
+/// use dir_size::growth_rate;
+/// use std::time::{Duration, SystemTime};
+///
+/// let start = SystemTime::UNIX_EPOCH;
+/// let later = start + Duration::from_secs(10);
+/// assert_eq!(growth_rate((start, 1000), (later, 1500)), 50.0);
+/// assert_eq!(growth_rate((start, 1000), (later, 500)), -50.0);
+/// assert_eq!(growth_rate((start, 1000), (start, 1500)), 0.0);
+/// ```
+pub fn growth_rate(prev: (SystemTime, u64), curr: (SystemTime, u64)) -> f64 {
+    let (elapsed, growth) = match curr.0.duration_since(prev.0) {
+        Ok(elapsed) => (elapsed, curr.1 as f64 - prev.1 as f64),
+        Err(e) => (e.duration(), prev.1 as f64 - curr.1 as f64),
+    };
+    if elapsed.is_zero() {
+        return 0.0;
+    }
+    growth / elapsed.as_secs_f64()
+}
+
+/// Get how long after `curr` the size reaches `threshold` (in bytes), at the rate it grew since `prev`.
+///
+/// This projects the [`growth_rate`] between the two measurements linearly, e.g. to estimate
+/// when a disk fills up. Returns [`Duration::ZERO`] if the size already reached `threshold`
+/// at `curr`, and `None` if it never does: when it does not grow or shrinks, when no time
+/// elapsed between the measurements, or when the projection does not fit in a [`Duration`].
+/// Measurements passed in reverse order are swapped, so the projection always starts from
+/// the later one.
+///
+/// ```
+/// use dir_size::time_to_threshold;
+/// use std::time::{Duration, SystemTime};
+///
+/// let start = SystemTime::UNIX_EPOCH;
+/// let later = start + Duration::from_secs(10);
+/// assert_eq!(
+///     time_to_threshold((start, 1000), (later, 1500), 2000),
+///     Some(Duration::from_secs(10))
+/// );
+/// assert_eq!(time_to_threshold((start, 1000), (later, 500), 2000), None);
+/// ```
+pub fn time_to_threshold(
+    prev: (SystemTime, u64),
+    curr: (SystemTime, u64),
+    threshold: u64,
+) -> Option<Duration> {
+    let (prev, curr) = if curr.0 < prev.0 {
+        (curr, prev)
+    } else {
+        (prev, curr)
+    };
+    if curr.1 >= threshold {
+        return Some(Duration::ZERO);
+    }
+    let rate = growth_rate(prev, curr);
+    if rate <= 0.0 {
+        return None;
+    }
+    Duration::try_from_secs_f64((threshold - curr.1) as f64 / rate).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_growth_rate() {
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+
+        assert_eq!(growth_rate((at(0), 0), (at(4), 1000)), 250.0);
+        assert_eq!(growth_rate((at(4), 1000), (at(0), 0)), 250.0);
+        assert_eq!(growth_rate((at(0), 1000), (at(4), 0)), -250.0);
+        assert_eq!(growth_rate((at(0), 1000), (at(4), 1000)), 0.0);
+        assert_eq!(growth_rate((at(4), 0), (at(4), 1000)), 0.0);
+        assert_eq!(growth_rate((at(0), 0), (at(1), u64::MAX)), u64::MAX as f64);
+    }
+
+    #[test]
+    fn test_time_to_threshold() {
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+
+        assert_eq!(
+            time_to_threshold((at(0), 0), (at(4), 1000), 3000),
+            Some(Duration::from_secs(8))
+        );
+        assert_eq!(
+            time_to_threshold((at(0), 0), (at(4), 1000), 1000),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            time_to_threshold((at(0), 1000), (at(4), 1000), 900),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            time_to_threshold((at(4), 1000), (at(0), 0), 3000),
+            Some(Duration::from_secs(8))
+        );
+        assert_eq!(
+            time_to_threshold((at(4), 1000), (at(0), 0), 500),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(time_to_threshold((at(0), 1000), (at(4), 1000), 3000), None);
+        assert_eq!(time_to_threshold((at(0), 2000), (at(4), 1000), 3000), None);
+        assert_eq!(time_to_threshold((at(4), 0), (at(4), 1000), 3000), None);
+        assert_eq!(
+            time_to_threshold((at(0), 0), (at(1 << 32), 1), u64::MAX),
+            None
+        );
+    }
+}
@@ -0,0 +1,35 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Small, stable, non-cryptographic hashing helpers.
+//!
+//! Unlike `std`'s `DefaultHasher`, the output is guaranteed not to change between
+//! Rust releases or runs, which matters wherever a hash decides or is persisted.
+
+use std::path::Path;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// Fold `bytes` into an FNV-1a hash state.
+pub(crate) fn fnv1a(state: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(state, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Hash a path together with `seed`, giving a well-mixed 64-bit value.
+pub(crate) fn hash_path(seed: u64, path: &Path) -> u64 {
+    mix(fnv1a(
+        FNV_OFFSET_BASIS ^ seed,
+        path.as_os_str().as_encoded_bytes(),
+    ))
+}
+
+/// The SplitMix64 finalizer, spreading every input bit over the whole output.
+pub(crate) fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
@@ -0,0 +1,73 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    walk::{Visitor, Walker},
+    ErrorPolicy, SizeOptions,
+};
+use std::{
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// List the entries below the file that cannot be accessed, along with the kind of error.
+///
+/// The directory is walked like [`get_size_in_bytes`](crate::get_size_in_bytes) does,
+/// without summing any size, to find every entry whose metadata or listing fails,
+/// e.g. because of missing permissions or because it was removed during the walk.
+/// The walk carries on with every other readable directory, so only the entries that
+/// really cannot be reached are missing, and the result is sorted by path.
+/// Memory grows with the number of inaccessible entries.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_inaccessible(path: &Path) -> io::Result<Vec<(PathBuf, io::ErrorKind)>> {
+    let options = SizeOptions::new()
+        .error_policy(ErrorPolicy::Collect)
+        .vanished_as_errors(true);
+    let walker = Walker::new(&options);
+    walker.run(path, &NoSize)?;
+
+    let mut inaccessible: Vec<_> = walker
+        .into_errors()
+        .into_iter()
+        .map(|(path, error)| (path, error.kind()))
+        .collect();
+    inaccessible.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    Ok(inaccessible)
+}
+
+struct NoSize;
+
+impl Visitor for NoSize {
+    type Acc = ();
+
+    fn init(&self) {}
+
+    fn visit_file(&self, _acc: &mut (), _path: &Path, _meta: &Metadata, _size: u64) {}
+
+    fn merge(&self, _a: (), _b: ()) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    #[test]
+    fn test_get_inaccessible() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(get_inaccessible(dir.path()).unwrap().is_empty());
+        assert!(get_inaccessible(&dir.path().join("missing")).is_err());
+
+        let Some(dir) = test_util::tree_with_unreadable_dir() else {
+            return;
+        };
+        assert_eq!(
+            get_inaccessible(dir.path()).unwrap(),
+            [(dir.path().join("locked"), io::ErrorKind::PermissionDenied)]
+        );
+    }
+}
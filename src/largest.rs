@@ -0,0 +1,371 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    top::TopN,
+    walk::{normalize, Visitor, Walker},
+    Error, SizeOptions,
+};
+use std::{
+    cmp::Reverse,
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Find the `n` largest files, returning their paths and sizes (in bytes).
+///
+/// If `path` points to a directory, all of its files and the files of its subdirectories
+/// are ranked. The result is sorted by descending size, and files of equal size are
+/// sorted by path, which also decides which of them make it into the `n` largest.
+/// The output is therefore identical between runs on an unchanged tree,
+/// regardless of the order in which the parallel walk visits the files.
+///
+/// Only `n` files are tracked at any time, so memory usage does not grow with the tree.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn find_largest_files(path: &Path, n: usize) -> io::Result<Vec<(PathBuf, u64)>> {
+    Ok(find_largest_files_with(path, n, &SizeOptions::new())?)
+}
+
+/// Find the `n` largest files like [`find_largest_files`], walking the tree according to `options`.
+///
+/// Sizes are measured as set by [`SizeOptions::size_kind`], and files skipped by the
+/// options are not ranked.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn find_largest_files_with(
+    path: &Path,
+    n: usize,
+    options: &SizeOptions,
+) -> Result<Vec<(PathBuf, u64)>, Error> {
+    Ok(Walker::new(options)
+        .run(path, &LargestFiles { n })?
+        .into_sorted_vec()
+        .into_iter()
+        .map(|(size, Reverse(path))| (path, size))
+        .collect())
+}
+
+/// Find every directory whose recursive size (in bytes) exceeds `threshold`, returning their paths and sizes.
+///
+/// This is the directory-level counterpart of [`find_largest_files`]. The size of a directory
+/// includes all of its files and subdirectories, so a large directory and its large
+/// subdirectories all appear in the result, and the walked path itself is included if it
+/// exceeds `threshold`. Only directories strictly larger than `threshold` are returned.
+/// The result is sorted by descending size, and directories of equal size are sorted by path.
+///
+/// Memory grows with the number of directories exceeding `threshold`.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn find_large_dirs(path: &Path, threshold: u64) -> io::Result<Vec<(PathBuf, u64)>> {
+    Ok(find_large_dirs_with(path, threshold, &SizeOptions::new())?)
+}
+
+/// Find every directory larger than `threshold` like [`find_large_dirs`], walking the tree according to `options`.
+///
+/// Sizes are measured as set by [`SizeOptions::size_kind`], and entries skipped by the
+/// options neither count towards the size of their directories nor are returned.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn find_large_dirs_with(
+    path: &Path,
+    threshold: u64,
+    options: &SizeOptions,
+) -> Result<Vec<(PathBuf, u64)>, Error> {
+    let (_, mut dirs) = Walker::new(options).run(path, &LargeDirs { threshold })?;
+    dirs.sort_unstable_by(|(a_path, a_size), (b_path, b_size)| {
+        b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+    });
+    Ok(dirs)
+}
+
+/// The largest files and directories of a tree, see [`top_consumers`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopConsumers {
+    /// The largest files and their sizes (in bytes), largest first.
+    pub files: Vec<(PathBuf, u64)>,
+    /// The largest directories below the walked path and their recursive sizes (in bytes), largest first.
+    pub dirs: Vec<(PathBuf, u64)>,
+    /// Size of the walked path (in bytes).
+    pub total: u64,
+}
+
+/// Find the `n` largest files and the `n` largest directories in a single walk, along with the total size (in bytes).
+///
+/// This combines [`find_largest_files`] with a ranking of directories by recursive size, which
+/// includes all of their files and subdirectories. A large file thus also makes its directory
+/// and every directory above it large, so the listed directories can overlap each other and
+/// contain listed files, and their sizes must not be added up. The walked path itself is not
+/// ranked among the directories, since its size is [`TopConsumers::total`]. Both lists are
+/// sorted by descending size, and entries of equal size are sorted by path.
+///
+/// Only `n` files and `n` directories are tracked at any time, so memory usage does not grow
+/// with the tree.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn top_consumers(path: &Path, n: usize) -> io::Result<TopConsumers> {
+    Ok(top_consumers_with(path, n, &SizeOptions::new())?)
+}
+
+/// Find the `n` largest files and directories like [`top_consumers`], walking the tree according to `options`.
+///
+/// Sizes are measured as set by [`SizeOptions::size_kind`], and entries skipped by the
+/// options neither count towards any size nor are ranked.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn top_consumers_with(
+    path: &Path,
+    n: usize,
+    options: &SizeOptions,
+) -> Result<TopConsumers, Error> {
+    let visitor = Consumers {
+        root: normalize(path),
+        files: LargestFiles { n },
+    };
+    let (total, files, dirs) = Walker::new(options).run(path, &visitor)?;
+    let sorted = |top: TopN<(u64, Reverse<PathBuf>)>| {
+        top.into_sorted_vec()
+            .into_iter()
+            .map(|(size, Reverse(path))| (path, size))
+            .collect()
+    };
+    Ok(TopConsumers {
+        files: sorted(files),
+        dirs: sorted(dirs),
+        total,
+    })
+}
+
+struct LargestFiles {
+    n: usize,
+}
+
+impl Visitor for LargestFiles {
+    type Acc = TopN<(u64, Reverse<PathBuf>)>;
+
+    fn init(&self) -> Self::Acc {
+        TopN::new(self.n)
+    }
+
+    fn visit_file(&self, acc: &mut Self::Acc, path: &Path, _meta: &Metadata, size: u64) {
+        // Only allocate the path when the file can make it into the top `n`.
+        if acc
+            .threshold()
+            .is_none_or(|(threshold, _)| size >= *threshold)
+        {
+            acc.push((size, Reverse(path.to_path_buf())));
+        }
+    }
+
+    fn merge(&self, a: Self::Acc, b: Self::Acc) -> Self::Acc {
+        a.merge(b)
+    }
+}
+
+struct LargeDirs {
+    threshold: u64,
+}
+
+impl Visitor for LargeDirs {
+    /// Size of the subtree, and the large directories found in it.
+    type Acc = (u64, Vec<(PathBuf, u64)>);
+
+    fn init(&self) -> Self::Acc {
+        (0, Vec::new())
+    }
+
+    fn visit_file(&self, acc: &mut Self::Acc, _path: &Path, _meta: &Metadata, size: u64) {
+        acc.0 += size;
+    }
+
+    fn leave_dir(&self, acc: &mut Self::Acc, path: &Path, _meta: &Metadata) {
+        if acc.0 > self.threshold {
+            acc.1.push((path.to_path_buf(), acc.0));
+        }
+    }
+
+    fn merge(&self, (a_size, mut a_dirs): Self::Acc, (b_size, b_dirs): Self::Acc) -> Self::Acc {
+        a_dirs.extend(b_dirs);
+        (a_size + b_size, a_dirs)
+    }
+}
+
+struct Consumers {
+    root: PathBuf,
+    files: LargestFiles,
+}
+
+impl Visitor for Consumers {
+    /// Size of the subtree, and the largest files and directories found in it.
+    type Acc = (
+        u64,
+        TopN<(u64, Reverse<PathBuf>)>,
+        TopN<(u64, Reverse<PathBuf>)>,
+    );
+
+    fn init(&self) -> Self::Acc {
+        (0, self.files.init(), TopN::new(self.files.n))
+    }
+
+    fn visit_file(&self, acc: &mut Self::Acc, path: &Path, meta: &Metadata, size: u64) {
+        acc.0 += size;
+        self.files.visit_file(&mut acc.1, path, meta, size);
+    }
+
+    fn leave_dir(&self, acc: &mut Self::Acc, path: &Path, _meta: &Metadata) {
+        if path != self.root
+            && acc
+                .2
+                .threshold()
+                .is_none_or(|(threshold, _)| acc.0 >= *threshold)
+        {
+            acc.2.push((acc.0, Reverse(path.to_path_buf())));
+        }
+    }
+
+    fn merge(&self, a: Self::Acc, b: Self::Acc) -> Self::Acc {
+        (a.0 + b.0, a.1.merge(b.1), a.2.merge(b.2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_find_largest_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        for (name, size) in [
+            ("small", 1),
+            ("sub/big", 100),
+            ("tie_c", 50),
+            ("sub/tie_b", 50),
+            ("tie_a", 50),
+        ] {
+            fs::write(dir.path().join(name), vec![0; size]).unwrap();
+        }
+
+        let expected = [
+            (dir.path().join("sub/big"), 100),
+            (dir.path().join("sub/tie_b"), 50),
+            (dir.path().join("tie_a"), 50),
+            (dir.path().join("tie_c"), 50),
+            (dir.path().join("small"), 1),
+        ];
+        for _ in 0..10 {
+            assert_eq!(find_largest_files(dir.path(), 3).unwrap(), expected[..3]);
+        }
+        assert_eq!(find_largest_files(dir.path(), 10).unwrap(), expected);
+        assert!(find_largest_files(dir.path(), 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_find_large_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("big/bigger")).unwrap();
+        fs::create_dir(dir.path().join("small")).unwrap();
+        fs::create_dir(dir.path().join("tie")).unwrap();
+        for (name, size) in [
+            ("big/a", 100),
+            ("big/bigger/b", 200),
+            ("small/c", 10),
+            ("tie/d", 300),
+        ] {
+            fs::write(dir.path().join(name), vec![0; size]).unwrap();
+        }
+
+        assert_eq!(
+            find_large_dirs(dir.path(), 100).unwrap(),
+            [
+                (dir.path().to_path_buf(), 610),
+                (dir.path().join("big"), 300),
+                (dir.path().join("tie"), 300),
+                (dir.path().join("big/bigger"), 200),
+            ]
+        );
+        assert_eq!(
+            find_large_dirs(dir.path(), 300).unwrap(),
+            [(dir.path().to_path_buf(), 610)]
+        );
+        assert!(find_large_dirs(dir.path(), 610).unwrap().is_empty());
+        assert!(find_large_dirs(&dir.path().join("big/a"), 0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_top_consumers() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("big/bigger")).unwrap();
+        fs::create_dir(dir.path().join("small")).unwrap();
+        fs::create_dir(dir.path().join("tie")).unwrap();
+        for (name, size) in [
+            ("root", 5),
+            ("big/a", 100),
+            ("big/bigger/b", 200),
+            ("small/c", 10),
+            ("tie/d", 300),
+        ] {
+            fs::write(dir.path().join(name), vec![0; size]).unwrap();
+        }
+
+        assert_eq!(
+            top_consumers(dir.path(), 3).unwrap(),
+            TopConsumers {
+                files: vec![
+                    (dir.path().join("tie/d"), 300),
+                    (dir.path().join("big/bigger/b"), 200),
+                    (dir.path().join("big/a"), 100),
+                ],
+                dirs: vec![
+                    (dir.path().join("big"), 300),
+                    (dir.path().join("tie"), 300),
+                    (dir.path().join("big/bigger"), 200),
+                ],
+                total: 615,
+            }
+        );
+        assert_eq!(
+            top_consumers(dir.path(), 0).unwrap(),
+            TopConsumers {
+                total: 615,
+                ..TopConsumers::default()
+            }
+        );
+        assert_eq!(
+            top_consumers(&dir.path().join("big/a"), 3).unwrap(),
+            TopConsumers {
+                files: vec![(dir.path().join("big/a"), 100)],
+                dirs: vec![],
+                total: 100,
+            }
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let top = TopConsumers {
+            files: vec![("dir/file".into(), 100)],
+            dirs: vec![("dir".into(), 150)],
+            total: 200,
+        };
+        let json = serde_json::to_string(&top).unwrap();
+        assert_eq!(
+            json,
+            r#"{"files":[["dir/file",100]],"dirs":[["dir",150]],"total":200}"#
+        );
+        assert_eq!(serde_json::from_str::<TopConsumers>(&json).unwrap(), top);
+    }
+}
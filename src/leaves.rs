@@ -0,0 +1,134 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    walk::{Visitor, Walker},
+    Error, SizeOptions,
+};
+use std::{
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Get the size (in bytes) of the files directly inside every leaf directory, sorted by path.
+///
+/// A leaf directory is a directory without any subdirectory, whether or not it holds files,
+/// so an empty directory is a leaf of size 0, and a directory holding nothing but another
+/// empty directory is not a leaf. Symlinks to directories are not followed and do not count
+/// as subdirectories. This tells apart the directories actually holding data, e.g. the
+/// partitions of a date-partitioned dataset, from the directories organizing them, whose
+/// own files are not reported. The walked path itself is returned if it is a leaf, and
+/// nothing is returned if it is not a directory. Subdirectories that cannot be read are
+/// neither returned nor count as subdirectories of their parent.
+///
+/// Memory grows with the number of leaf directories.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_leaf_dir_sizes(path: &Path) -> io::Result<Vec<(PathBuf, u64)>> {
+    Ok(get_leaf_dir_sizes_with(path, &SizeOptions::new())?)
+}
+
+/// Get the size (in bytes) of every leaf directory like [`get_leaf_dir_sizes`], walking the tree according to `options`.
+///
+/// Sizes are measured as set by [`SizeOptions::size_kind`]. Directories not descended into
+/// are neither returned nor count as subdirectories of their parent, and files skipped by
+/// the options are not counted.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_leaf_dir_sizes_with(
+    path: &Path,
+    options: &SizeOptions,
+) -> Result<Vec<(PathBuf, u64)>, Error> {
+    let mut leaves = Walker::new(options).run(path, &Leaves)?.leaves;
+    leaves.sort_unstable();
+    Ok(leaves)
+}
+
+#[derive(Default)]
+struct Sizes {
+    /// Bytes of the files directly inside the directory being walked.
+    bytes: u64,
+    /// Whether a subdirectory of the directory being walked was completed.
+    has_subdir: bool,
+    /// Leaf directories completed so far.
+    leaves: Vec<(PathBuf, u64)>,
+}
+
+struct Leaves;
+
+impl Visitor for Leaves {
+    type Acc = Sizes;
+
+    fn init(&self) -> Sizes {
+        Sizes::default()
+    }
+
+    fn visit_file(&self, acc: &mut Sizes, _path: &Path, _meta: &Metadata, size: u64) {
+        acc.bytes += size;
+    }
+
+    fn leave_dir(&self, acc: &mut Sizes, path: &Path, _meta: &Metadata) {
+        let bytes = std::mem::take(&mut acc.bytes);
+        if !acc.has_subdir {
+            acc.leaves.push((path.to_path_buf(), bytes));
+        }
+        // Once merged into the parent, this directory makes it a non-leaf.
+        acc.has_subdir = true;
+    }
+
+    fn merge(&self, mut a: Sizes, b: Sizes) -> Sizes {
+        a.bytes += b.bytes;
+        a.has_subdir |= b.has_subdir;
+        a.leaves.extend(b.leaves);
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_get_leaf_dir_sizes() {
+        let dir = tempfile::tempdir().unwrap();
+        for sub in ["2024/01", "2024/02", "2025/01/empty", "2025/02", "misc"] {
+            fs::create_dir_all(dir.path().join(sub)).unwrap();
+        }
+        for (name, size) in [
+            ("index", 1),
+            ("2024/summary", 10),
+            ("2024/01/data", 100),
+            ("2024/02/data", 200),
+            ("2024/02/more", 300),
+            ("2025/01/data", 400),
+            ("misc/notes", 5),
+        ] {
+            fs::write(dir.path().join(name), vec![0; size]).unwrap();
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("../2024", dir.path().join("misc/link")).unwrap();
+
+        assert_eq!(
+            get_leaf_dir_sizes(dir.path()).unwrap(),
+            [
+                (dir.path().join("2024/01"), 100),
+                (dir.path().join("2024/02"), 500),
+                (dir.path().join("2025/01/empty"), 0),
+                (dir.path().join("2025/02"), 0),
+                (dir.path().join("misc"), 5),
+            ]
+        );
+        assert_eq!(
+            get_leaf_dir_sizes(&dir.path().join("2024/02")).unwrap(),
+            [(dir.path().join("2024/02"), 500)]
+        );
+        assert!(get_leaf_dir_sizes(&dir.path().join("index"))
+            .unwrap()
+            .is_empty());
+    }
+}
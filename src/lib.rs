@@ -2,145 +2,658 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
-use rayon::prelude::*;
-use std::{fs, io, path::Path};
+//! Parallelized directory size calculation.
+//!
+//! # Paths
+//!
+//! Walked paths are normalized lexically first, so every spelling of the same path gives
+//! the same result: trailing and repeated separators and `.` components are dropped, and
+//! `dir/`, `dir/.` and `./dir` are all walked as `dir`. In particular, a trailing slash
+//! does not make a walked symlink followed. `..` components are kept and resolved by the
+//! filesystem, so `link/..` is the parent of the target of `link`, not the directory holding
+//! `link`. The paths returned by a walk, e.g. by [`collect_entries`], start with the
+//! normalized walked path.
+//!
+//! # Memory usage
+//!
+//! A walk keeps the listing of every directory between the walked path and the entries
+//! currently being processed, so all functions need memory proportional to the depth of
+//! the tree times the size of its largest directories. On top of that, each public function
+//! documents which of these classes it belongs to:
+//!
+//! - **Bounded memory**: nothing is kept per file, so memory does not grow with the number
+//!   of files. This covers plain sums, counts, capped sizes, estimates,
+//!   [`approximate_median_size`] and [`walk_dir_totals`].
+//! - **Memory proportional to an argument**: only a fixed number of files is tracked at any
+//!   time, e.g. `n` for [`find_largest_files`], [`top_consumers`] and [`size_of_newest_files`],
+//!   `k` for [`sample_files`], or the number of buckets for [`get_size_by_age`].
+//! - **Memory proportional to the number of groups**: breakdowns such as
+//!   [`get_size_by_extension`] and [`group_sizes_by`] keep one total per group, not per file.
+//! - **Unbounded memory**: [`collect_entries`] and [`size_map`] keep every entry,
+//!   [`find_large_dirs`] keeps every directory above its threshold,
+//!   [`get_dir_density`] and [`resume_size`] keep every directory walked,
+//!   [`get_leaf_dir_sizes`] every leaf directory,
+//!   [`verify_against_manifest`] and `watch_size` keep every file,
+//!   [`get_physical_size`] and [`SizeKind::Physical`] remember every shared extent, and
+//!   [`ErrorPolicy::Collect`] and [`get_inaccessible`] keep every error encountered.
+//!
+//! Where an exact answer would need every file at once, a bounded alternative is provided,
+//! such as [`approximate_median_size`] instead of an exact median and [`sample_files`]
+//! for a uniform sample of example files.
 
-const KIBIBYTE: u64 = 1 << 10;
-const MEBIBYTE: u64 = 1 << 20;
-const GIBIBYTE: u64 = 1 << 30;
-const TEBIBYTE: u64 = 1 << 40;
-const PEBIBYTE: u64 = 1 << 50;
-const EXBIBYTE: u64 = 1 << 60;
+mod age;
+#[cfg(feature = "archive")]
+mod archive;
+mod baseline;
+mod breakdown;
+mod density;
+mod entries;
+mod error;
+mod estimate;
+#[cfg(all(target_os = "linux", feature = "fifo"))]
+mod fifo;
+mod format;
+mod growth;
+mod hash;
+mod inaccessible;
+mod largest;
+mod leaves;
+mod manifest;
+mod median;
+mod metrics;
+mod newest;
+mod options;
+mod physical;
+mod readable;
+mod resume;
+mod roots;
+mod signature;
+#[cfg(test)]
+mod test_util;
+mod top;
+mod totals;
+mod usage;
+mod walk;
+#[cfg(feature = "watch")]
+mod watch;
+
+pub use age::{get_size_by_age, get_size_in_window, get_size_older_than};
+#[cfg(feature = "archive")]
+pub use archive::get_archive_uncompressed_size;
+pub use baseline::{size_since_baseline, size_with_baseline, SizeDelta};
+#[cfg(unix)]
+pub use breakdown::get_size_by_owner;
+pub use breakdown::{
+    get_size_by_extension, get_size_by_extension_with, get_size_by_file_kind, group_sizes_by,
+    group_sizes_by_with, ExtensionMode, FileKind,
+};
+pub use density::{get_dir_density, get_dir_density_with};
+pub use entries::{collect_entries, size_map};
+pub use error::Error;
+pub use estimate::{estimate_size, estimate_size_seeded, sample_files};
+pub use format::{
+    format_in_unit, format_in_unit_with, format_size, format_size_verbose,
+    get_sizes_in_common_unit, Alignment, FormatStyle, Rounding, Unit, UnitStandard,
+};
+pub use growth::{growth_rate, time_to_threshold};
+pub use inaccessible::get_inaccessible;
+pub use largest::{
+    find_large_dirs, find_large_dirs_with, find_largest_files, find_largest_files_with,
+    top_consumers, top_consumers_with, TopConsumers,
+};
+pub use leaves::{get_leaf_dir_sizes, get_leaf_dir_sizes_with};
+pub use manifest::{verify_against_manifest, Discrepancy};
+pub use median::approximate_median_size;
+pub use metrics::{get_size_in_bytes_timed, WalkMetrics};
+pub use newest::size_of_newest_files;
+pub use options::{
+    DescendFilter, DirCallback, ErrorCallback, ErrorPolicy, SizeKind, SizeOptions, SizeReport,
+};
+pub use physical::get_physical_size;
+pub use resume::{resume_size, resume_size_with};
+pub use roots::{get_roots_breakdown, RootsReport};
+pub use signature::structure_signature;
+pub use totals::walk_dir_totals;
+pub use usage::{get_data_and_overhead, get_usage, get_usage_with, DataAndOverhead, Usage};
+#[cfg(feature = "watch")]
+pub use watch::{watch_size, SizeWatcher};
+
+use std::{
+    fs::Metadata,
+    io,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use walk::{walk, Visitor, Walker};
 
 /// Get the size of the file (in bytes).
 ///
 /// If `path` points to a directory, calculate the size of directory recursively,
 /// including all of its files and subdirectories.
+/// Runs in bounded memory.
 ///
 /// This function will return an error if `path` does not exist,
 /// or user lacks permissions to perform `metadata` call on `path`.
 pub fn get_size_in_bytes(path: &Path) -> io::Result<u64> {
-    match fs::symlink_metadata(path) {
-        Ok(meta) if meta.is_file() => Ok(meta.len()),
-        Ok(meta) if meta.is_dir() => get_dir_size(path),
-        Ok(_) => Ok(0),
-        Err(e) => Err(e),
-    }
+    walk(path, &TotalSize)
 }
 
-/// Get the size of the file (in human-readable bytes).
+/// Get the size of the file (in bytes), or 0 if it cannot be determined.
+///
+/// This works like [`get_size_in_bytes`], but hides every error: entries inside the directory
+/// that cannot be accessed are skipped, so the result may be a partial total, and if `path`
+/// itself cannot be accessed, 0 is returned. A result of 0 thus does not tell an empty file
+/// from a missing one, so only use it where a best-effort number is good enough, e.g. logging.
+/// Runs in bounded memory.
+pub fn get_size_or_zero(path: &Path) -> u64 {
+    get_size_in_bytes(path).unwrap_or(0)
+}
+
+/// Get the size of the file (in bytes), walking the directory according to `options`.
+///
+/// If `path` points to a directory, calculate the size of directory recursively,
+/// including all of its files and subdirectories.
+/// Errors on entries inside the directory are handled according to the configured [`ErrorPolicy`].
+/// Runs in bounded memory, except with [`ErrorPolicy::Collect`] which keeps every error.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_size_with_options(path: &Path, options: &SizeOptions) -> Result<SizeReport, Error> {
+    let walker = Walker::new(options);
+    let bytes = walker.run(path, &TotalSize)?;
+    let (races, truncated) = (walker.races(), walker.truncated());
+    Ok(SizeReport {
+        bytes,
+        errors: walker.into_errors(),
+        races,
+        truncated,
+    })
+}
+
+/// Get the size of the file (in bytes), but at most `cap`.
 ///
 /// If `path` points to a directory, calculate the size of directory recursively,
 /// including all of its files and subdirectories.
+/// The walk stops as soon as the running total reaches `cap` and `cap` is returned,
+/// so a large directory can be checked against a limit without walking all of it.
+///
+/// Since entries are visited in parallel and in no particular order, the entries seen
+/// before stopping vary between calls. On a tree that is not modified concurrently the
+/// result is `min(size, cap)`, but with concurrent modifications it is only an approximation,
+/// as the unvisited remainder of the tree is assumed to keep the total above `cap`.
+///
+/// Runs in bounded memory.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_size_capped(path: &Path, cap: u64) -> io::Result<u64> {
+    let visitor = CappedSize {
+        total: AtomicU64::new(0),
+        cap,
+    };
+    walk(path, &visitor)?;
+    Ok(visitor.total.into_inner().min(cap))
+}
+
+/// Get the size of the file (in human-readable bytes).
+///
+/// If `path` points to a directory, calculate the size of directory recursively,
+/// including all of its files and subdirectories. The size is formatted with the default
+/// [`FormatStyle`]; use [`format_size`] on [`get_size_in_bytes`] for any other style.
+/// Runs in bounded memory.
 ///
 /// This function will return an error if `path` does not exist,
 /// or user lacks permissions to perform `metadata` call on `path`.
 pub fn get_size_in_human_bytes(path: &Path) -> io::Result<String> {
-    Ok(convert_to_human_bytes(get_size_in_bytes(path)?, false))
+    get_size_in_human_bytes_with(path, UnitStandard::Iec)
+}
+
+/// Get the size of the file (in human-readable bytes, using the units of `standard`).
+///
+/// If `path` points to a directory, calculate the size of directory recursively,
+/// including all of its files and subdirectories.
+/// Runs in bounded memory.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_size_in_human_bytes_with(path: &Path, standard: UnitStandard) -> io::Result<String> {
+    Ok(format_size(
+        get_size_in_bytes(path)?,
+        &FormatStyle::new().standard(standard),
+    ))
 }
 
 /// Get the size of the file (in human-readable bytes, using abbreviated units (K, M, G, etc.))
 ///
 /// If `path` points to a directory, calculate the size of directory recursively,
 /// including all of its files and subdirectories.
+/// Runs in bounded memory.
 ///
 /// This function will return an error if `path` does not exist,
 /// or user lacks permissions to perform `metadata` call on `path`.
 pub fn get_size_in_abbr_human_bytes(path: &Path) -> io::Result<String> {
-    Ok(convert_to_human_bytes(get_size_in_bytes(path)?, true))
+    Ok(format_size(
+        get_size_in_bytes(path)?,
+        &FormatStyle::new().abbreviate(true),
+    ))
 }
 
-fn get_dir_size(path: &Path) -> io::Result<u64> {
-    let entries: Vec<_> = fs::read_dir(path)?.collect();
-
-    let total = entries
-        .par_iter()
-        .filter_map(|entry| match entry {
-            Ok(entry) => match entry.metadata() {
-                Ok(meta) if meta.is_file() => Some(meta.len()),
-                Ok(meta) if meta.is_dir() => get_dir_size(&entry.path()).ok(),
-                _ => None,
-            },
-            _ => None,
-        })
-        .sum();
-
-    Ok(total)
+/// Get the size of the file (in human-readable bytes), or `0 Bytes` if it cannot be determined.
+///
+/// This is the human-readable counterpart of [`get_size_or_zero`], and hides every error in the same way.
+/// Runs in bounded memory.
+pub fn get_size_in_human_bytes_or_zero(path: &Path) -> String {
+    format_size(get_size_or_zero(path), &FormatStyle::new())
 }
 
-fn convert_to_human_bytes(size_in_bytes: u64, abbr: bool) -> String {
-    for ((min_bytes, max_bytes), abbr_unit, full_unit) in [
-        ((1, KIBIBYTE), "B", "Bytes"),      // Bytes
-        ((KIBIBYTE, MEBIBYTE), "K", "KiB"), // KiB
-        ((MEBIBYTE, GIBIBYTE), "M", "MiB"), // MiB
-        ((GIBIBYTE, TEBIBYTE), "G", "GiB"), // GiB
-        ((TEBIBYTE, PEBIBYTE), "T", "TiB"), // TiB
-        ((PEBIBYTE, EXBIBYTE), "P", "PiB"), // PiB
-    ] {
-        if size_in_bytes < max_bytes {
-            return format!(
-                "{} {}",
-                size_in_bytes / min_bytes,
-                if abbr { abbr_unit } else { full_unit }
-            );
-        }
+struct TotalSize;
+
+impl Visitor for TotalSize {
+    type Acc = u64;
+
+    fn init(&self) -> u64 {
+        0
     }
 
-    format!(
-        "{} {}",
-        size_in_bytes / EXBIBYTE,
-        if abbr { "E" } else { "EiB" }
-    )
+    fn visit_file(&self, acc: &mut u64, _path: &Path, _meta: &Metadata, size: u64) {
+        *acc += size;
+    }
+
+    fn merge(&self, a: u64, b: u64) -> u64 {
+        a + b
+    }
+}
+
+struct CappedSize {
+    total: AtomicU64,
+    cap: u64,
+}
+
+impl Visitor for CappedSize {
+    type Acc = ();
+
+    fn init(&self) {}
+
+    fn visit_file(&self, _acc: &mut (), _path: &Path, _meta: &Metadata, size: u64) {
+        self.total.fetch_add(size, Ordering::Relaxed);
+    }
+
+    fn merge(&self, _a: (), _b: ()) {}
+
+    fn is_done(&self) -> bool {
+        self.total.load(Ordering::Relaxed) >= self.cap
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    #[test]
+    fn test_get_size_in_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("file"), [0; 100]).unwrap();
+        fs::write(dir.path().join("a/file"), [0; 200]).unwrap();
+        fs::write(dir.path().join("a/b/file"), [0; 300]).unwrap();
+
+        assert_eq!(get_size_in_bytes(dir.path()).unwrap(), 600);
+        assert_eq!(get_size_in_bytes(&dir.path().join("a/file")).unwrap(), 200);
+        assert!(get_size_in_bytes(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_get_size_or_zero() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file"), [0; 100]).unwrap();
+
+        assert_eq!(get_size_or_zero(dir.path()), 100);
+        assert_eq!(get_size_or_zero(&dir.path().join("missing")), 0);
+        assert_eq!(get_size_in_human_bytes_or_zero(dir.path()), "100 Bytes");
+        assert_eq!(
+            get_size_in_human_bytes_or_zero(&dir.path().join("missing")),
+            "0 Bytes"
+        );
+    }
 
     #[test]
-    fn test_convert_to_human_bytes() {
-        for (size_in_bytes, human_bytes) in [
-            (0, "0 Bytes"),
-            (KIBIBYTE - 1, "1023 Bytes"),
-            (KIBIBYTE, "1 KiB"),
-            (MEBIBYTE - 1, "1023 KiB"),
-            (MEBIBYTE, "1 MiB"),
-            (GIBIBYTE - 1, "1023 MiB"),
-            (GIBIBYTE, "1 GiB"),
-            (TEBIBYTE - 1, "1023 GiB"),
-            (TEBIBYTE, "1 TiB"),
-            (PEBIBYTE - 1, "1023 TiB"),
-            (PEBIBYTE, "1 PiB"),
-            (EXBIBYTE - 1, "1023 PiB"),
-            (EXBIBYTE, "1 EiB"),
-        ] {
-            println!("{size_in_bytes} bytes -> {human_bytes}");
-            assert_eq!(convert_to_human_bytes(size_in_bytes, false), human_bytes);
+    fn test_get_size_capped() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in 0..100 {
+            fs::write(dir.path().join(file.to_string()), [0; 10]).unwrap();
         }
+
+        assert_eq!(get_size_capped(dir.path(), 0).unwrap(), 0);
+        assert_eq!(get_size_capped(dir.path(), 55).unwrap(), 55);
+        assert_eq!(get_size_capped(dir.path(), 1000).unwrap(), 1000);
+        assert_eq!(get_size_capped(dir.path(), 5000).unwrap(), 1000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_count_symlink_size() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file"), [0; 100]).unwrap();
+        std::os::unix::fs::symlink("0123456789", dir.path().join("link")).unwrap();
+
+        let report = get_size_with_options(dir.path(), &SizeOptions::new()).unwrap();
+        assert_eq!(report.bytes, 100);
+
+        let options = SizeOptions::new().count_symlink_size(true);
+        let report = get_size_with_options(dir.path(), &options).unwrap();
+        assert_eq!(report.bytes, 110);
+        let report = get_size_with_options(&dir.path().join("link"), &options).unwrap();
+        assert_eq!(report.bytes, 10);
+    }
+
+    #[test]
+    fn test_path_spellings() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/file"), [0; 100]).unwrap();
+        fs::write(dir.path().join("file"), [0; 10]).unwrap();
+
+        let sub = dir.path().join("sub");
+        let entries = |path: &Path| {
+            let mut entries = collect_entries(path, &SizeOptions::new()).unwrap();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            entries
+                .into_iter()
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>()
+        };
+        for spelling in ["sub/", "sub/.", "./sub", "sub//", "./sub/./"] {
+            let path = dir.path().join(spelling);
+            assert_eq!(get_size_in_bytes(&path).unwrap(), 100, "{spelling}");
+            assert_eq!(entries(&path), entries(&sub), "{spelling}");
+        }
+        assert_eq!(entries(&sub), [sub.clone(), sub.join("file")]);
+        // `..` is resolved by the filesystem, not lexically.
+        assert_eq!(get_size_in_bytes(&sub.join("..")).unwrap(), 110);
+        assert_eq!(get_size_in_bytes(&sub.join("../")).unwrap(), 110);
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&sub, dir.path().join("link")).unwrap();
+            // A trailing slash does not make the walked symlink followed.
+            assert_eq!(get_size_in_bytes(&dir.path().join("link/")).unwrap(), 0);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fallback_to_len() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file"), [0; 100]).unwrap();
+        let _socket = std::os::unix::net::UnixListener::bind(dir.path().join("socket")).unwrap();
+        let counted = |options: &SizeOptions| {
+            collect_entries(dir.path(), options)
+                .unwrap()
+                .iter()
+                .any(|(path, _)| path.ends_with("socket"))
+        };
+
+        assert!(!counted(&SizeOptions::new()));
+        assert!(counted(&SizeOptions::new().fallback_to_len(true)));
+        let options = SizeOptions::new().fallback_to_len(true);
+        assert_eq!(
+            get_size_with_options(dir.path(), &options).unwrap().bytes,
+            100
+        );
+    }
+
+    #[test]
+    fn test_size_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file"), [0; 100]).unwrap();
+        let sparse = fs::File::create(dir.path().join("sparse")).unwrap();
+        sparse.set_len(1 << 20).unwrap();
+
+        let size = |size_kind| {
+            let options = SizeOptions::new().size_kind(size_kind);
+            get_size_with_options(dir.path(), &options).unwrap().bytes
+        };
+        assert_eq!(size(SizeKind::Apparent), 100 + (1 << 20));
+        assert_eq!(
+            size(SizeKind::Physical),
+            get_physical_size(dir.path()).unwrap()
+        );
+        #[cfg(unix)]
+        {
+            // The data of the small file takes at least one block, the sparse file none.
+            let allocated = size(SizeKind::Allocated);
+            assert!(allocated >= 512);
+            assert!(allocated < 1 << 20);
+        }
+    }
+
+    #[test]
+    fn test_size_kind_in_reports() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/file"), [0; 100]).unwrap();
+        let sparse = fs::File::create(dir.path().join("sparse")).unwrap();
+        sparse.set_len(1 << 20).unwrap();
+
+        let options = SizeOptions::new().size_kind(SizeKind::Allocated);
+        let size = |path: &Path| get_size_with_options(path, &options).unwrap().bytes;
+        let (file, sparse) = (dir.path().join("sub/file"), dir.path().join("sparse"));
+        let files = [(file.clone(), size(&file)), (sparse.clone(), size(&sparse))];
+        let total = files[0].1 + files[1].1;
+
+        let mut largest = find_largest_files_with(dir.path(), 2, &options).unwrap();
+        largest.sort_unstable();
+        let mut expected = files.to_vec();
+        expected.sort_unstable();
+        assert_eq!(largest, expected);
+        assert_eq!(
+            top_consumers_with(dir.path(), 2, &options).unwrap().total,
+            total
+        );
+        assert_eq!(
+            find_large_dirs_with(dir.path(), 0, &options).unwrap()[0],
+            (dir.path().to_path_buf(), total)
+        );
+        assert_eq!(
+            group_sizes_by_with(dir.path(), |_, _| (), &options).unwrap()[&()],
+            total
+        );
+        assert_eq!(get_usage_with(dir.path(), &options).unwrap().bytes, total);
+        assert!(get_dir_density_with(dir.path(), &options)
+            .unwrap()
+            .contains(&(dir.path().join("sub"), 1, files[0].1)));
+        assert_eq!(
+            get_leaf_dir_sizes_with(dir.path(), &options).unwrap(),
+            [(dir.path().join("sub"), files[0].1)]
+        );
+    }
+
+    #[test]
+    fn test_on_dir_complete() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::create_dir(dir.path().join("empty")).unwrap();
+        fs::write(dir.path().join("file"), [0; 1]).unwrap();
+        fs::write(dir.path().join("a/file"), [0; 10]).unwrap();
+        fs::write(dir.path().join("a/b/file"), [0; 100]).unwrap();
+        for i in 0..100 {
+            fs::create_dir(dir.path().join(format!("a/{i}"))).unwrap();
+        }
+
+        let completed = Arc::new(Mutex::new(Vec::new()));
+        let options = SizeOptions::new().on_dir_complete({
+            let completed = Arc::clone(&completed);
+            move |path, bytes| completed.lock().unwrap().push((path.to_path_buf(), bytes))
+        });
+        assert_eq!(
+            get_size_with_options(dir.path(), &options).unwrap().bytes,
+            111
+        );
+
+        let completed = completed.lock().unwrap();
+        assert_eq!(completed.len(), 1 + 3 + 100);
+        let position = |path: &Path| completed.iter().position(|(p, _)| p == path).unwrap();
+        assert!(position(&dir.path().join("a/b")) < position(&dir.path().join("a")));
+        assert_eq!(completed.last().unwrap(), &(dir.path().to_path_buf(), 111));
+        assert!(completed.contains(&(dir.path().join("a"), 110)));
+        assert!(completed.contains(&(dir.path().join("a/b"), 100)));
+        assert!(completed.contains(&(dir.path().join("empty"), 0)));
+
+        // Once truncated, partial sizes are not reported as final.
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        for i in 0..100 {
+            fs::write(dir.path().join(format!("sub/{i}")), [0; 1]).unwrap();
+        }
+        let completed = Arc::new(Mutex::new(Vec::new()));
+        let options = SizeOptions::new().max_entries(50).on_dir_complete({
+            let completed = Arc::clone(&completed);
+            move |path, bytes| completed.lock().unwrap().push((path.to_path_buf(), bytes))
+        });
+        assert!(
+            get_size_with_options(dir.path(), &options)
+                .unwrap()
+                .truncated
+        );
+        assert!(completed.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_include_hidden() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".hidden/.git")).unwrap();
+        fs::create_dir(dir.path().join("visible")).unwrap();
+        fs::write(dir.path().join("file"), [0; 1]).unwrap();
+        fs::write(dir.path().join(".file"), [0; 10]).unwrap();
+        fs::write(dir.path().join("visible/.file"), [0; 100]).unwrap();
+        fs::write(dir.path().join(".hidden/file"), [0; 1000]).unwrap();
+        fs::write(dir.path().join(".hidden/.git/file"), [0; 10000]).unwrap();
+
+        let size = |path: &Path, include_hidden| {
+            let options = SizeOptions::new().include_hidden(include_hidden);
+            get_size_with_options(path, &options).unwrap().bytes
+        };
+        assert_eq!(size(dir.path(), true), 11111);
+        assert_eq!(size(dir.path(), false), 1);
+        assert_eq!(size(&dir.path().join(".hidden"), false), 1000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks_within_root() {
+        use std::os::unix::fs::symlink;
+
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("file"), [0; 1000]).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("a/file"), [0; 10]).unwrap();
+        fs::write(dir.path().join("a/b/file"), [0; 100]).unwrap();
+        // Followed: a file and a directory within the root.
+        symlink("a/file", dir.path().join("file_link")).unwrap();
+        symlink("a/b", dir.path().join("dir_link")).unwrap();
+        // Skipped: leading outside of the root, dangling, or looping back to an ancestor.
+        symlink(outside.path(), dir.path().join("outside_link")).unwrap();
+        symlink(outside.path().join("file"), dir.path().join("outside_file")).unwrap();
+        symlink("missing", dir.path().join("dangling")).unwrap();
+        symlink("..", dir.path().join("a/b/parent")).unwrap();
+        symlink(".", dir.path().join("a/self")).unwrap();
+
+        let size =
+            |options: &SizeOptions| get_size_with_options(dir.path(), options).unwrap().bytes;
+        let options = SizeOptions::new().follow_symlinks_within_root(true);
+        assert_eq!(size(&SizeOptions::new()), 110);
+        assert_eq!(size(&options), 110 + 10 + 100);
+        assert_eq!(
+            get_size_with_options(&dir.path().join("a"), &options)
+                .unwrap()
+                .bytes,
+            110
+        );
     }
 
     #[test]
-    fn test_convert_to_abbr_human_bytes() {
-        for (size_in_bytes, abbr_human_bytes) in [
-            (0, "0 B"),
-            (KIBIBYTE - 1, "1023 B"),
-            (KIBIBYTE, "1 K"),
-            (MEBIBYTE - 1, "1023 K"),
-            (MEBIBYTE, "1 M"),
-            (GIBIBYTE - 1, "1023 M"),
-            (GIBIBYTE, "1 G"),
-            (TEBIBYTE - 1, "1023 G"),
-            (TEBIBYTE, "1 T"),
-            (PEBIBYTE - 1, "1023 T"),
-            (PEBIBYTE, "1 P"),
-            (EXBIBYTE - 1, "1023 P"),
-            (EXBIBYTE, "1 E"),
-        ] {
-            println!("{size_in_bytes} bytes -> {abbr_human_bytes}");
-            assert_eq!(
-                convert_to_human_bytes(size_in_bytes, true),
-                abbr_human_bytes
-            );
+    fn test_descend_into() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("keep/skip")).unwrap();
+        fs::create_dir(dir.path().join("skip")).unwrap();
+        fs::write(dir.path().join("keep/file"), [0; 100]).unwrap();
+        fs::write(dir.path().join("keep/skip/file"), [0; 200]).unwrap();
+        fs::write(dir.path().join("skip/file"), [0; 400]).unwrap();
+
+        let options = SizeOptions::new()
+            .stay_on_filesystem(true)
+            .descend_into(|path, meta| {
+                assert!(meta.is_dir());
+                path.file_name().unwrap() != "skip"
+            });
+        let report = get_size_with_options(dir.path(), &options).unwrap();
+        assert_eq!(report.bytes, 100);
+
+        let options = SizeOptions::new().stay_on_filesystem(true);
+        let report = get_size_with_options(dir.path(), &options).unwrap();
+        assert_eq!(report.bytes, 700);
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..10 {
+            fs::write(dir.path().join(i.to_string()), [0; 10]).unwrap();
         }
+
+        let size = |max_entries| {
+            let options = SizeOptions::new().max_entries(max_entries);
+            let report = get_size_with_options(dir.path(), &options).unwrap();
+            (report.bytes, report.truncated)
+        };
+        assert_eq!(size(10), (100, false));
+        assert_eq!(size(9), (90, true));
+        assert_eq!(size(3), (30, true));
+        assert_eq!(size(0), (0, true));
+
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/file"), [0; 10]).unwrap();
+        assert_eq!(size(12), (110, false));
+        let (bytes, truncated) = size(11);
+        assert!(bytes <= 100 && truncated);
+    }
+
+    #[test]
+    fn test_error_policy() {
+        let Some(dir) = test_util::tree_with_unreadable_dir() else {
+            return;
+        };
+        let locked = dir.path().join("locked");
+
+        let report = get_size_with_options(dir.path(), &SizeOptions::new()).unwrap();
+        assert_eq!(report.bytes, 100);
+        assert!(report.errors.is_empty());
+
+        let options = SizeOptions::new().error_policy(ErrorPolicy::Collect);
+        let report = get_size_with_options(dir.path(), &options).unwrap();
+        assert_eq!(report.bytes, 100);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, locked);
+        assert_eq!(report.errors[0].1.kind(), io::ErrorKind::PermissionDenied);
+
+        let options = SizeOptions::new().error_policy(ErrorPolicy::FailFast);
+        let error = get_size_with_options(dir.path(), &options).unwrap_err();
+        assert!(matches!(error, Error::Io(e) if e.kind() == io::ErrorKind::PermissionDenied));
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&calls);
+        let options =
+            SizeOptions::new().error_policy(ErrorPolicy::Callback(Box::new(move |path, _| {
+                recorded.lock().unwrap().push(path.to_path_buf())
+            })));
+        let report = get_size_with_options(dir.path(), &options).unwrap();
+        assert_eq!(report.bytes, 100);
+        assert!(report.errors.is_empty());
+        assert_eq!(*calls.lock().unwrap(), [locked]);
     }
 }
@@ -0,0 +1,221 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    walk::{normalize, Visitor, Walker},
+    Error, ErrorPolicy, SizeOptions,
+};
+use std::{
+    collections::HashMap,
+    fs::Metadata,
+    path::{Path, PathBuf},
+};
+
+/// A difference between the files found on disk and those declared in a manifest,
+/// see [`verify_against_manifest`].
+///
+/// Paths are relative to the verified root, like those of the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Discrepancy {
+    /// The file exists, but its size differs from the declared one.
+    SizeMismatch {
+        /// Path of the file.
+        path: PathBuf,
+        /// Size declared in the manifest (in bytes).
+        expected: u64,
+        /// Size found on disk (in bytes).
+        actual: u64,
+    },
+    /// The file is declared in the manifest, but was not found on disk.
+    Missing {
+        /// Path of the file.
+        path: PathBuf,
+        /// Size declared in the manifest (in bytes).
+        expected: u64,
+    },
+    /// The file was found on disk, but is not declared in the manifest.
+    Extra {
+        /// Path of the file.
+        path: PathBuf,
+        /// Size found on disk (in bytes).
+        actual: u64,
+    },
+}
+
+impl Discrepancy {
+    /// The path of the file, relative to the verified root.
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::SizeMismatch { path, .. }
+            | Self::Missing { path, .. }
+            | Self::Extra { path, .. } => path,
+        }
+    }
+}
+
+/// Compare the files below `path` and their sizes (in bytes) against those declared in `manifest`.
+///
+/// The paths of `manifest` are relative to `path`, e.g. `bin/tool` for the file
+/// `path/bin/tool`, and are normalized like walked paths, see the [crate documentation](crate#paths),
+/// so `./bin/tool` and `bin//tool` match it too. They are then compared with the paths found
+/// on disk component by component, without resolving `..` or symlinks, and discrepancies
+/// report them normalized. The comparison is case-sensitive, even on filesystems
+/// that are not, so `README` does not match a file named `readme`. Only counted files are
+/// compared: directories are implied by the files they contain, and symlinks and special files
+/// are not reported as extra. If a path is declared several times, each declaration is checked.
+///
+/// Returns every discrepancy sorted by path, so an empty result means that the tree matches
+/// the manifest exactly. Memory grows with the number of files.
+///
+/// This function will return an error if `path` does not exist, or if any entry below it
+/// cannot be read, since the files it holds could otherwise be reported as missing.
+pub fn verify_against_manifest(
+    path: &Path,
+    manifest: &[(PathBuf, u64)],
+) -> Result<Vec<Discrepancy>, Error> {
+    let options = SizeOptions::new().error_policy(ErrorPolicy::FailFast);
+    let mut found = Walker::new(&options).run(
+        path,
+        &FileSizes {
+            root: &normalize(path),
+        },
+    )?;
+
+    let mut discrepancies: Vec<_> = manifest
+        .iter()
+        .filter_map(|(path, expected)| {
+            let (path, expected) = (normalize(path), *expected);
+            match found.get(&path) {
+                None => Some(Discrepancy::Missing { path, expected }),
+                Some(&actual) if actual != expected => Some(Discrepancy::SizeMismatch {
+                    path,
+                    expected,
+                    actual,
+                }),
+                Some(_) => None,
+            }
+        })
+        .collect();
+    for (path, _) in manifest {
+        found.remove(&normalize(path));
+    }
+    discrepancies.extend(
+        found
+            .into_iter()
+            .map(|(path, actual)| Discrepancy::Extra { path, actual }),
+    );
+    discrepancies.sort_by(|a, b| a.path().cmp(b.path()));
+    Ok(discrepancies)
+}
+
+struct FileSizes<'a> {
+    root: &'a Path,
+}
+
+impl Visitor for FileSizes<'_> {
+    type Acc = HashMap<PathBuf, u64>;
+
+    fn init(&self) -> Self::Acc {
+        HashMap::new()
+    }
+
+    fn visit_file(&self, acc: &mut Self::Acc, path: &Path, _meta: &Metadata, size: u64) {
+        let relative = path.strip_prefix(self.root).unwrap_or(path);
+        acc.insert(relative.to_path_buf(), size);
+    }
+
+    fn merge(&self, a: Self::Acc, b: Self::Acc) -> Self::Acc {
+        let (mut larger, smaller) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+        larger.extend(smaller);
+        larger
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_verify_against_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("bin")).unwrap();
+        fs::create_dir(dir.path().join("empty")).unwrap();
+        fs::write(dir.path().join("bin/tool"), [0; 100]).unwrap();
+        fs::write(dir.path().join("README"), [0; 10]).unwrap();
+        fs::write(dir.path().join("extra"), [0; 1]).unwrap();
+
+        let manifest = [
+            ("bin/tool".into(), 100),
+            ("readme".into(), 10),
+            ("missing".into(), 5),
+        ];
+        assert_eq!(
+            verify_against_manifest(dir.path(), &manifest).unwrap(),
+            [
+                Discrepancy::Extra {
+                    path: "README".into(),
+                    actual: 10
+                },
+                Discrepancy::Extra {
+                    path: "extra".into(),
+                    actual: 1
+                },
+                Discrepancy::Missing {
+                    path: "missing".into(),
+                    expected: 5
+                },
+                Discrepancy::Missing {
+                    path: "readme".into(),
+                    expected: 10
+                },
+            ]
+        );
+
+        let manifest = [
+            ("README".into(), 10),
+            ("bin/tool".into(), 99),
+            ("extra".into(), 1),
+        ];
+        assert_eq!(
+            verify_against_manifest(dir.path(), &manifest).unwrap(),
+            [Discrepancy::SizeMismatch {
+                path: "bin/tool".into(),
+                expected: 99,
+                actual: 100
+            }]
+        );
+        assert_eq!(
+            verify_against_manifest(&dir.path().join("bin"), &[("tool".into(), 100)]).unwrap(),
+            []
+        );
+
+        let manifest = [
+            ("./README".into(), 10),
+            ("bin//tool".into(), 100),
+            ("./extra/".into(), 1),
+        ];
+        assert_eq!(verify_against_manifest(dir.path(), &manifest).unwrap(), []);
+        assert!(
+            verify_against_manifest(dir.path(), &[("./missing".into(), 5)])
+                .unwrap()
+                .contains(&Discrepancy::Missing {
+                    path: "missing".into(),
+                    expected: 5
+                })
+        );
+    }
+
+    #[test]
+    fn test_verify_unreadable_dir() {
+        let Some(dir) = crate::test_util::tree_with_unreadable_dir() else {
+            return;
+        };
+
+        let manifest = [("file".into(), 100), ("locked/file".into(), 100)];
+        let error = verify_against_manifest(dir.path(), &manifest).unwrap_err();
+        assert!(matches!(error, Error::Io(e) if e.kind() == std::io::ErrorKind::PermissionDenied));
+    }
+}
@@ -0,0 +1,123 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::walk::{walk, Visitor};
+use std::{fs::Metadata, io, path::Path};
+
+/// Sizes below this are tracked exactly, larger ones are split into this many buckets per power of two.
+const SUB_BUCKETS: u64 = 16;
+const SUB_BUCKET_BITS: u32 = SUB_BUCKETS.trailing_zeros();
+const BUCKETS: usize = (SUB_BUCKETS + (64 - SUB_BUCKET_BITS as u64) * SUB_BUCKETS) as usize;
+
+/// Get the approximate median size of the files (in bytes).
+///
+/// If `path` points to a directory, the sizes of all of its files and the files of its
+/// subdirectories are considered. Returns `None` if there is no file.
+///
+/// Instead of keeping every size, sizes are counted in a fixed histogram of logarithmic
+/// buckets, so the result is within about 3% of the exact median (and exact below 16 bytes).
+/// Runs in bounded memory.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn approximate_median_size(path: &Path) -> io::Result<Option<u64>> {
+    let histogram = walk(path, &SizeHistogram)?;
+    let count: u64 = histogram.iter().sum();
+    if count == 0 {
+        return Ok(None);
+    }
+
+    // The lower median, i.e. the element at index `(count - 1) / 2` when sorted.
+    let rank = (count - 1) / 2;
+    let mut seen = 0;
+    for (bucket, &files) in histogram.iter().enumerate() {
+        seen += files;
+        if seen > rank {
+            return Ok(Some(bucket_value(bucket)));
+        }
+    }
+    unreachable!("the rank is below the total count")
+}
+
+/// Index of the bucket holding `size`.
+fn bucket_of(size: u64) -> usize {
+    if size < SUB_BUCKETS {
+        return size as usize;
+    }
+    let exponent = 63 - size.leading_zeros();
+    let shift = exponent - SUB_BUCKET_BITS;
+    let mantissa = (size >> shift) & (SUB_BUCKETS - 1);
+    (SUB_BUCKETS + u64::from(shift) * SUB_BUCKETS + mantissa) as usize
+}
+
+/// Middle of the range of sizes falling into `bucket`.
+fn bucket_value(bucket: usize) -> u64 {
+    let bucket = bucket as u64;
+    if bucket < SUB_BUCKETS {
+        return bucket;
+    }
+    let shift = (bucket - SUB_BUCKETS) / SUB_BUCKETS;
+    let mantissa = (bucket - SUB_BUCKETS) % SUB_BUCKETS;
+    let lower = (SUB_BUCKETS + mantissa) << shift;
+    lower + ((1 << shift) - 1) / 2
+}
+
+struct SizeHistogram;
+
+impl Visitor for SizeHistogram {
+    type Acc = Box<[u64; BUCKETS]>;
+
+    fn init(&self) -> Self::Acc {
+        Box::new([0; BUCKETS])
+    }
+
+    fn visit_file(&self, acc: &mut Self::Acc, _path: &Path, _meta: &Metadata, size: u64) {
+        acc[bucket_of(size)] += 1;
+    }
+
+    fn merge(&self, mut a: Self::Acc, b: Self::Acc) -> Self::Acc {
+        a.iter_mut().zip(b.iter()).for_each(|(a, b)| *a += b);
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_buckets() {
+        for size in [0, 1, 15, 16, 17, 1000, 123_456_789, u64::MAX] {
+            let value = bucket_value(bucket_of(size));
+            assert_eq!(bucket_of(value), bucket_of(size));
+            assert!(size.abs_diff(value) as f64 <= size as f64 / 32.0);
+        }
+        assert_eq!(bucket_of(u64::MAX), BUCKETS - 1);
+    }
+
+    #[test]
+    fn test_approximate_median_size() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(approximate_median_size(dir.path()).unwrap(), None);
+
+        for size in 1..=9 {
+            fs::write(dir.path().join(size.to_string()), vec![0; size]).unwrap();
+        }
+        assert_eq!(approximate_median_size(dir.path()).unwrap(), Some(5));
+
+        for size in [10_000, 20_000, 30_000] {
+            fs::write(dir.path().join(size.to_string()), vec![0; size]).unwrap();
+        }
+        // The twelve sizes have 6 and 7 as their lower and upper median.
+        assert_eq!(approximate_median_size(dir.path()).unwrap(), Some(6));
+
+        let dir = tempfile::tempdir().unwrap();
+        for size in [1000, 2000, 3000] {
+            fs::write(dir.path().join(size.to_string()), vec![0; size]).unwrap();
+        }
+        let median = approximate_median_size(dir.path()).unwrap().unwrap();
+        assert!(median.abs_diff(2000) <= 2000 / 32);
+    }
+}
@@ -0,0 +1,111 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::walk::{walk, Visitor};
+use std::{
+    fs::Metadata,
+    io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// Throughput of a walk, to compare how fast different storage can be scanned.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WalkMetrics {
+    /// Wall-clock time taken by the walk.
+    pub elapsed: Duration,
+    /// Number of files counted.
+    pub files: u64,
+    /// Number of directories walked, including `path` itself.
+    pub dirs: u64,
+    /// Files counted per second of `elapsed`, or 0 if no time elapsed.
+    pub files_per_sec: f64,
+}
+
+/// Get the size of the file (in bytes), along with metrics about how fast it was walked.
+///
+/// If `path` points to a directory, calculate the size of directory recursively,
+/// including all of its files and subdirectories.
+/// The elapsed time is wall-clock time, measured around the whole walk, so it includes
+/// the overhead of scheduling work on rayon's threads and anything else the machine was
+/// doing at the same time. Runs in bounded memory.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_size_in_bytes_timed(path: &Path) -> io::Result<(u64, WalkMetrics)> {
+    let start = Instant::now();
+    let counts = walk(path, &CountingSize)?;
+    let elapsed = start.elapsed();
+
+    let files_per_sec = if elapsed.is_zero() {
+        0.0
+    } else {
+        counts.files as f64 / elapsed.as_secs_f64()
+    };
+    Ok((
+        counts.bytes,
+        WalkMetrics {
+            elapsed,
+            files: counts.files,
+            dirs: counts.dirs,
+            files_per_sec,
+        },
+    ))
+}
+
+#[derive(Default)]
+struct Counts {
+    bytes: u64,
+    files: u64,
+    dirs: u64,
+}
+
+struct CountingSize;
+
+impl Visitor for CountingSize {
+    type Acc = Counts;
+
+    fn init(&self) -> Counts {
+        Counts::default()
+    }
+
+    fn visit_file(&self, acc: &mut Counts, _path: &Path, _meta: &Metadata, size: u64) {
+        acc.bytes += size;
+        acc.files += 1;
+    }
+
+    fn leave_dir(&self, acc: &mut Counts, _path: &Path, _meta: &Metadata) {
+        acc.dirs += 1;
+    }
+
+    fn merge(&self, a: Counts, b: Counts) -> Counts {
+        Counts {
+            bytes: a.bytes + b.bytes,
+            files: a.files + b.files,
+            dirs: a.dirs + b.dirs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_get_size_in_bytes_timed() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("file"), [0; 100]).unwrap();
+        fs::write(dir.path().join("a/b/file"), [0; 200]).unwrap();
+
+        let (bytes, metrics) = get_size_in_bytes_timed(dir.path()).unwrap();
+        assert_eq!(bytes, 300);
+        assert_eq!(metrics.files, 2);
+        assert_eq!(metrics.dirs, 3);
+        assert!(metrics.files_per_sec > 0.0);
+        assert!(metrics.elapsed > Duration::ZERO);
+    }
+}
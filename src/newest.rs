@@ -0,0 +1,96 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    top::TopN,
+    walk::{walk, Visitor},
+};
+use std::{
+    cmp::Reverse,
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Get the total size (in bytes) of the `n` most recently modified files.
+///
+/// If `path` points to a directory, its files and the files of its subdirectories are
+/// ranked by modification time. Files whose modification time is not available are skipped.
+/// When several files share a modification time, the ones with the lexicographically
+/// smaller path are considered newer, so the result does not depend on the walk order.
+///
+/// Only `n` files are tracked at any time, so memory usage does not grow with the tree.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn size_of_newest_files(path: &Path, n: usize) -> io::Result<u64> {
+    Ok(walk(path, &NewestFiles { n })?
+        .into_sorted_vec()
+        .into_iter()
+        .map(|(_, _, size)| size)
+        .sum())
+}
+
+struct NewestFiles {
+    n: usize,
+}
+
+type Newest = (SystemTime, Reverse<PathBuf>, u64);
+
+impl Visitor for NewestFiles {
+    type Acc = TopN<Newest>;
+
+    fn init(&self) -> Self::Acc {
+        TopN::new(self.n)
+    }
+
+    fn visit_file(&self, acc: &mut Self::Acc, path: &Path, meta: &Metadata, size: u64) {
+        let Ok(modified) = meta.modified() else {
+            return;
+        };
+        // Only allocate the path when the file can make it into the top `n`.
+        if acc
+            .threshold()
+            .is_none_or(|(threshold, _, _)| modified >= *threshold)
+        {
+            acc.push((modified, Reverse(path.to_path_buf()), size));
+        }
+    }
+
+    fn merge(&self, a: Self::Acc, b: Self::Acc) -> Self::Acc {
+        a.merge(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, time::Duration};
+
+    #[test]
+    fn test_size_of_newest_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        let base = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        for (name, size, age) in [
+            ("old", 1, 30),
+            ("sub/older", 2, 40),
+            ("new", 10, 1),
+            ("sub/newer", 20, 2),
+            ("tie_a", 100, 3),
+            ("tie_b", 1000, 3),
+        ] {
+            let file = fs::File::create(dir.path().join(name)).unwrap();
+            file.set_len(size).unwrap();
+            file.set_modified(base - Duration::from_secs(age)).unwrap();
+        }
+
+        assert_eq!(size_of_newest_files(dir.path(), 0).unwrap(), 0);
+        assert_eq!(size_of_newest_files(dir.path(), 2).unwrap(), 30);
+        // `tie_a` wins the tie with `tie_b` because of its smaller path.
+        assert_eq!(size_of_newest_files(dir.path(), 3).unwrap(), 130);
+        assert_eq!(size_of_newest_files(dir.path(), 100).unwrap(), 1133);
+    }
+}
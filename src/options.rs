@@ -0,0 +1,366 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{
+    fmt,
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Callback receiving the path and error of an inaccessible entry.
+pub type ErrorCallback = Box<dyn Fn(&Path, &io::Error) + Send + Sync>;
+
+/// Predicate deciding whether the walk descends into a directory.
+pub type DescendFilter = Box<dyn Fn(&Path, &Metadata) -> bool + Send + Sync>;
+
+/// Callback receiving the path and recursive size (in bytes) of a completely walked directory.
+pub type DirCallback = Box<dyn Fn(&Path, u64) + Send + Sync>;
+
+/// How errors on entries below the walked path are handled.
+///
+/// Errors on the walked path itself are always returned to the caller.
+#[derive(Default)]
+pub enum ErrorPolicy {
+    /// Skip inaccessible entries silently. This is the default.
+    #[default]
+    Ignore,
+    /// Skip inaccessible entries and record them in [`SizeReport::errors`].
+    Collect,
+    /// Abort the walk on the first inaccessible entry and return its error.
+    FailFast,
+    /// Skip inaccessible entries and pass each of them to the callback.
+    ///
+    /// The callback may be invoked concurrently from several threads.
+    Callback(ErrorCallback),
+}
+
+impl fmt::Debug for ErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ignore => f.write_str("Ignore"),
+            Self::Collect => f.write_str("Collect"),
+            Self::FailFast => f.write_str("FailFast"),
+            Self::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+/// What the size of a file is measured as, see [`SizeOptions::size_kind`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SizeKind {
+    /// The length of the file, as reported by its metadata. This is the default.
+    ///
+    /// This matches `du --apparent-size`, and is supported on every platform.
+    #[default]
+    Apparent,
+    /// The space allocated to the file on disk, which is usually rounded up to whole blocks,
+    /// and smaller than the length for sparse or transparently compressed files.
+    ///
+    /// This matches `du`, and is only supported on Unix, where it is the number of 512-byte
+    /// blocks reported by the metadata. On other platforms, the length is used instead.
+    Allocated,
+    /// The space used on disk by the data of the file, counting data shared between files
+    /// once, see [`get_physical_size`](crate::get_physical_size).
+    ///
+    /// This is only supported for regular files, on Linux with filesystems supporting the
+    /// `FIEMAP` ioctl, and on macOS with APFS. Otherwise, the length is used instead.
+    /// Remembering the shared data makes memory grow with the number of shared extents
+    /// or clones found.
+    Physical,
+}
+
+/// Options controlling how a directory is walked.
+///
+/// ```
+/// use dir_size::{ErrorPolicy, SizeOptions};
+///
+/// let options = SizeOptions::new().error_policy(ErrorPolicy::Collect);
+/// ```
+#[derive(Default)]
+pub struct SizeOptions {
+    pub(crate) error_policy: ErrorPolicy,
+    pub(crate) size_kind: SizeKind,
+    pub(crate) count_symlink_size: bool,
+    pub(crate) follow_symlinks_within_root: bool,
+    pub(crate) stay_on_filesystem: bool,
+    pub(crate) fallback_to_len: bool,
+    pub(crate) exclude_hidden: bool,
+    pub(crate) readable_only: bool,
+    #[cfg(all(target_os = "linux", feature = "fifo"))]
+    pub(crate) count_fifo_buffers: bool,
+    pub(crate) descend_into: Option<DescendFilter>,
+    pub(crate) on_dir_complete: Option<DirCallback>,
+    pub(crate) modified_after: Option<SystemTime>,
+    pub(crate) modified_before: Option<SystemTime>,
+    pub(crate) vanished_as_errors: bool,
+    pub(crate) max_entries: Option<usize>,
+}
+
+impl fmt::Debug for SizeOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut f = f.debug_struct("SizeOptions");
+        f.field("error_policy", &self.error_policy)
+            .field("size_kind", &self.size_kind)
+            .field("count_symlink_size", &self.count_symlink_size)
+            .field(
+                "follow_symlinks_within_root",
+                &self.follow_symlinks_within_root,
+            )
+            .field("stay_on_filesystem", &self.stay_on_filesystem)
+            .field("fallback_to_len", &self.fallback_to_len)
+            .field("include_hidden", &!self.exclude_hidden)
+            .field("readable_only", &self.readable_only)
+            .field("descend_into", &self.descend_into.as_ref().map(|_| ".."))
+            .field(
+                "on_dir_complete",
+                &self.on_dir_complete.as_ref().map(|_| ".."),
+            )
+            .field("modified_after", &self.modified_after)
+            .field("modified_before", &self.modified_before)
+            .field("vanished_as_errors", &self.vanished_as_errors)
+            .field("max_entries", &self.max_entries);
+        #[cfg(all(target_os = "linux", feature = "fifo"))]
+        f.field("count_fifo_buffers", &self.count_fifo_buffers);
+        f.finish()
+    }
+}
+
+impl SizeOptions {
+    /// Create options matching the behavior of [`get_size_in_bytes`](crate::get_size_in_bytes).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how errors on entries below the walked path are handled (default: [`ErrorPolicy::Ignore`]).
+    pub fn error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
+
+    /// Set what the size of each counted entry is measured as (default: [`SizeKind::Apparent`]).
+    ///
+    /// Every function taking options honors it, and passes the measured size to whatever it
+    /// computes from the sizes of the entries, e.g. [`find_largest_files_with`] ranks files
+    /// by their allocated size with [`SizeKind::Allocated`]. The functions without options,
+    /// such as [`find_largest_files`] or [`get_size_by_age`], always measure the apparent size.
+    ///
+    /// [`find_largest_files_with`]: crate::find_largest_files_with
+    /// [`find_largest_files`]: crate::find_largest_files
+    /// [`get_size_by_age`]: crate::get_size_by_age
+    pub fn size_kind(mut self, size_kind: SizeKind) -> Self {
+        self.size_kind = size_kind;
+        self
+    }
+
+    /// Set whether entries removed during the walk are handled as errors (default: `false`).
+    ///
+    /// On a live filesystem, an entry can be removed between the listing of its directory
+    /// and the access to its metadata. By default, such entries are not passed to the
+    /// error policy, so they do not abort a walk even with [`ErrorPolicy::FailFast`],
+    /// and are only counted in [`SizeReport::races`]. When enabled, they are handled
+    /// by the error policy like any other inaccessible entry.
+    pub fn vanished_as_errors(mut self, vanished_as_errors: bool) -> Self {
+        self.vanished_as_errors = vanished_as_errors;
+        self
+    }
+
+    /// Set whether symlinks count with their own size (default: `false`).
+    ///
+    /// Symlinks are not followed, unless [`follow_symlinks_within_root`](Self::follow_symlinks_within_root)
+    /// is set. When enabled, each symlink adds the size
+    /// reported by `symlink_metadata`, which on Unix is the length of its target path,
+    /// matching what `du --apparent-size` counts.
+    pub fn count_symlink_size(mut self, count_symlink_size: bool) -> Self {
+        self.count_symlink_size = count_symlink_size;
+        self
+    }
+
+    /// Set whether symlinks leading to a target within the walked path are followed (default: `false`).
+    ///
+    /// When enabled, symlinks whose canonical target lies below the canonical walked path count
+    /// as their target: a link to a file counts with the size of the file, and a link to a
+    /// directory is walked like the directory. This measures e.g. how large a backup
+    /// dereferencing symlinks would be, without ever pulling in system directories outside
+    /// the tree. As with such a backup, data reachable through several paths counts once per path.
+    ///
+    /// Symlinks leading out of the walked path and dangling symlinks are not followed,
+    /// and neither are symlinks to a directory being walked, such as a link to one of its
+    /// parents, which would never end. Those are handled as with the option disabled,
+    /// see [`count_symlink_size`](Self::count_symlink_size). The walked path itself is
+    /// never followed if it is a symlink.
+    pub fn follow_symlinks_within_root(mut self, follow_symlinks_within_root: bool) -> Self {
+        self.follow_symlinks_within_root = follow_symlinks_within_root;
+        self
+    }
+
+    /// Set whether to skip directories on other filesystems than the walked path (default: `false`).
+    ///
+    /// This is the equivalent of `du --one-file-system`, and is only supported on Unix.
+    /// On other platforms, it has no effect.
+    pub fn stay_on_filesystem(mut self, stay_on_filesystem: bool) -> Self {
+        self.stay_on_filesystem = stay_on_filesystem;
+        self
+    }
+
+    /// Set whether entries that are neither files, directories nor symlinks count with their length (default: `false`).
+    ///
+    /// By default, special files such as FIFOs, sockets and devices count as zero bytes.
+    /// Some virtual or overlay filesystems report entries that are neither regular files
+    /// nor directories but still hold data, which this option counts with the length
+    /// reported by their metadata. This risks counting data twice, or data that does not
+    /// exist on disk, since the length of such entries may describe e.g. a whole device
+    /// or data already counted through another path. Directories that are not descended
+    /// into are never counted.
+    pub fn fallback_to_len(mut self, fallback_to_len: bool) -> Self {
+        self.fallback_to_len = fallback_to_len;
+        self
+    }
+
+    /// Set whether hidden entries are counted (default: `true`).
+    ///
+    /// When disabled, entries whose name starts with a dot are skipped, and hidden directories
+    /// are not descended into, like `ls` and `du --exclude='.*'` do. On Windows, where hiding is
+    /// an attribute rather than a naming convention, entries with the hidden attribute are
+    /// skipped as well. The walked path itself is always walked, even if it is hidden.
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.exclude_hidden = !include_hidden;
+        self
+    }
+
+    /// Set whether only the files the current process can open for reading are counted (default: `false`).
+    ///
+    /// This measures e.g. how much of a tree a backup running as the current user can copy.
+    /// A file may be listed and have metadata while its content cannot be read, so when enabled,
+    /// the readability of every counted regular file is checked. On Linux, this asks the kernel
+    /// with `faccessat` against the effective user and group IDs, without opening the file.
+    /// On other platforms, the file is briefly opened for reading instead.
+    ///
+    /// Unreadable files are skipped and handled by the error policy like inaccessible entries,
+    /// so they can be collected or abort the walk. The walked path itself is always checked
+    /// if it is a file, and returns an error if it is unreadable. Symlinks and special files
+    /// counted by other options are not checked.
+    pub fn readable_only(mut self, readable_only: bool) -> Self {
+        self.readable_only = readable_only;
+        self
+    }
+
+    /// Set whether named pipes count with the data currently buffered in them (default: `false`).
+    ///
+    /// By default, named pipes count as zero bytes like other special files. When enabled,
+    /// each of them is briefly opened for reading without blocking, and counts with the
+    /// number of bytes waiting to be read, as reported by the `FIONREAD` ioctl. Nothing is
+    /// read, but opening a pipe may let a writer waiting for a reader proceed, and writers
+    /// may get `EPIPE` if the walk closes the only reader. Pipes that cannot be opened
+    /// count as zero bytes.
+    ///
+    /// The value is a momentary snapshot, which can change as soon as it is measured.
+    /// This is only available on Linux, with the `fifo` feature.
+    #[cfg(all(target_os = "linux", feature = "fifo"))]
+    pub fn count_fifo_buffers(mut self, count_fifo_buffers: bool) -> Self {
+        self.count_fifo_buffers = count_fifo_buffers;
+        self
+    }
+
+    /// Only descend into the directories for which `filter` returns `true` (default: all of them).
+    ///
+    /// `filter` receives the path and metadata of every directory below the walked path,
+    /// and can be used to decide which boundaries to cross, e.g. following bind mounts
+    /// but not network mounts. A rejected directory and everything below it is not counted.
+    /// These are directories themselves and never symlinks, which are not followed.
+    ///
+    /// When combined with [`stay_on_filesystem`](Self::stay_on_filesystem), a directory
+    /// must pass both checks to be walked, and `filter` is only called for directories
+    /// on the same filesystem as the walked path.
+    ///
+    /// `filter` may be called concurrently from several threads.
+    pub fn descend_into(
+        mut self,
+        filter: impl Fn(&Path, &Metadata) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.descend_into = Some(Box::new(filter));
+        self
+    }
+
+    /// Call `callback` with the recursive size (in bytes) of every directory as soon as it is final.
+    ///
+    /// `callback` is called exactly once for `path` itself and for every directory walked below
+    /// it, once its whole subtree has been walked, so a directory is always reported after all
+    /// of its subdirectories, and `path` itself last. Sibling directories are reported in no
+    /// particular order. The size is the one counted according to the other options.
+    /// Directories that are not descended into or that cannot be read are not reported,
+    /// and neither are the remaining directories once a walk is aborted. Once a walk is
+    /// truncated by [`SizeOptions::max_entries`], the directories completed from then on
+    /// are not reported either, since their size may be partial, so only final sizes are.
+    ///
+    /// `callback` may be called concurrently from several threads.
+    pub fn on_dir_complete(
+        mut self,
+        callback: impl Fn(&Path, u64) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_dir_complete = Some(Box::new(callback));
+        self
+    }
+
+    /// Only count files last modified at or after `time` (default: no lower bound).
+    ///
+    /// Files whose modification time is not available are not counted once a time bound is set.
+    /// Directories are always walked, since their own modification time says nothing about their files.
+    pub fn modified_after(mut self, time: SystemTime) -> Self {
+        self.modified_after = Some(time);
+        self
+    }
+
+    /// Only count files last modified strictly before `time` (default: no upper bound).
+    ///
+    /// Files whose modification time is not available are not counted once a time bound is set.
+    /// Directories are always walked, since their own modification time says nothing about their files.
+    pub fn modified_before(mut self, time: SystemTime) -> Self {
+        self.modified_before = Some(time);
+        self
+    }
+
+    /// Stop the walk once `max_entries` entries below the walked path have been visited (default: no limit).
+    ///
+    /// This bounds the cost of walking untrusted trees, such as extracted archives holding
+    /// millions of tiny or empty files. Every entry counts, whether a file, a directory or
+    /// a symlink, and whether it is counted or not. If any entries remain past the limit, they
+    /// are skipped and [`SizeReport::truncated`] is set, so the returned size is only
+    /// a lower bound of the actual one. Since entries are visited in parallel, which of them
+    /// are included in a truncated result varies between calls. Directories completed after
+    /// the limit is reached are not passed to [`SizeOptions::on_dir_complete`].
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Whether a non-directory entry passes the filters on its metadata.
+    pub(crate) fn matches(&self, meta: &Metadata) -> bool {
+        if self.modified_after.is_none() && self.modified_before.is_none() {
+            return true;
+        }
+        let Ok(modified) = meta.modified() else {
+            return false;
+        };
+        self.modified_after.is_none_or(|after| modified >= after)
+            && self.modified_before.is_none_or(|before| modified < before)
+    }
+}
+
+/// The result of [`get_size_with_options`](crate::get_size_with_options).
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct SizeReport {
+    /// Total size (in bytes).
+    pub bytes: u64,
+    /// Entries that could not be accessed, only filled in with [`ErrorPolicy::Collect`].
+    pub errors: Vec<(PathBuf, io::Error)>,
+    /// Number of entries removed between the listing of their directory and their access,
+    /// which are not errors unless [`SizeOptions::vanished_as_errors`] is set.
+    pub races: u64,
+    /// Whether the walk stopped early after [`SizeOptions::max_entries`] entries,
+    /// in which case [`bytes`](Self::bytes) is only a lower bound.
+    pub truncated: bool,
+}
@@ -0,0 +1,269 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{get_size_with_options, SizeKind, SizeOptions};
+use std::{fs::Metadata, io, path::Path};
+
+/// Get the physical size of the file (in bytes), counting data shared between files once.
+///
+/// If `path` points to a directory, calculate the size of directory recursively,
+/// including all of its files and subdirectories.
+///
+/// Files cloned with e.g. `cp --reflink` share their data on disk, so summing their
+/// apparent sizes overstates how much space they actually use. This function looks up
+/// where the data of each file is stored and counts every shared extent only once.
+/// The size of each file is rounded up to whole blocks, like its on-disk allocation.
+///
+/// This is best-effort and platform-specific:
+/// - On Linux, extents are queried through the `FIEMAP` ioctl, and extents flagged as shared
+///   (Btrfs, XFS with reflinks, ...) are deduplicated when they start at the same physical
+///   offset with the same length. Partially overlapping shared extents are counted separately.
+/// - On macOS, APFS reports through `getattrlist` how much of the allocation of a file is
+///   private to it, and a clone ID shared by files that are pure clones of each other. The
+///   private part of every file is counted, and the shared rest once per clone ID. Clones that
+///   have since diverged get different clone IDs, so the data they still share is counted
+///   once for each of them.
+/// - Elsewhere, and for files on filesystems supporting neither,
+///   the apparent size is used, as [`get_size_in_bytes`](crate::get_size_in_bytes) does.
+///
+/// Memory grows with the number of shared extents or clone IDs found, as each of them is
+/// remembered to avoid counting it twice.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_physical_size(path: &Path) -> io::Result<u64> {
+    let options = SizeOptions::new().size_kind(SizeKind::Physical);
+    Ok(get_size_with_options(path, &options)?.bytes)
+}
+
+/// The shared extents counted so far during a walk.
+#[derive(Default)]
+pub(crate) struct SharedExtents {
+    /// Shared extents already counted, as `(device, physical offset, length)`.
+    #[cfg(target_os = "linux")]
+    seen: std::sync::Mutex<std::collections::HashSet<(u64, u64, u64)>>,
+    /// Clones whose shared data was already counted, as `(device, clone ID)`.
+    #[cfg(target_os = "macos")]
+    seen: std::sync::Mutex<std::collections::HashSet<(u64, u64)>>,
+}
+
+impl SharedExtents {
+    /// The physical size of the regular file at `path`, not counting extents it shares
+    /// with a file seen before.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn physical_len(&self, path: &Path, meta: &Metadata) -> io::Result<u64> {
+        use std::os::unix::fs::MetadataExt;
+
+        let file = std::fs::File::open(path)?;
+        let mut total = 0;
+        fiemap::for_each_extent(&file, |extent| {
+            if extent.fe_flags & fiemap::FIEMAP_EXTENT_SHARED == 0
+                || self.seen.lock().unwrap().insert((
+                    meta.dev(),
+                    extent.fe_physical,
+                    extent.fe_length,
+                ))
+            {
+                total += extent.fe_length;
+            }
+        })?;
+        Ok(total)
+    }
+
+    #[cfg(target_os = "macos")]
+    pub(crate) fn physical_len(&self, path: &Path, meta: &Metadata) -> io::Result<u64> {
+        use std::os::unix::fs::MetadataExt;
+
+        let file = std::fs::File::open(path)?;
+        let clone = apfs::clone_info(&file)?;
+        let allocated = meta.blocks() * 512;
+        let private = clone.private_size.min(allocated);
+        if private == allocated
+            || self
+                .seen
+                .lock()
+                .unwrap()
+                .insert((meta.dev(), clone.clone_id))
+        {
+            Ok(allocated)
+        } else {
+            Ok(private)
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    pub(crate) fn physical_len(&self, _path: &Path, _meta: &Metadata) -> io::Result<u64> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod fiemap {
+    use std::{fs::File, io, os::fd::AsRawFd};
+
+    /// `_IOWR('f', 11, struct fiemap)`
+    const FS_IOC_FIEMAP: u32 = 0xc020_660b;
+    const FIEMAP_EXTENT_LAST: u32 = 0x0001;
+    pub(super) const FIEMAP_EXTENT_SHARED: u32 = 0x2000;
+
+    /// Number of extents fetched per ioctl call.
+    const BATCH: usize = 64;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    pub(super) struct FiemapExtent {
+        pub(super) fe_logical: u64,
+        pub(super) fe_physical: u64,
+        pub(super) fe_length: u64,
+        fe_reserved64: [u64; 2],
+        pub(super) fe_flags: u32,
+        fe_reserved: [u32; 3],
+    }
+
+    #[repr(C)]
+    struct Fiemap {
+        fm_start: u64,
+        fm_length: u64,
+        fm_flags: u32,
+        fm_mapped_extents: u32,
+        fm_extent_count: u32,
+        fm_reserved: u32,
+        fm_extents: [FiemapExtent; BATCH],
+    }
+
+    /// Call `f` with every extent of `file`, in logical order.
+    pub(super) fn for_each_extent(file: &File, mut f: impl FnMut(&FiemapExtent)) -> io::Result<()> {
+        let mut start = 0;
+        loop {
+            let mut map = Fiemap {
+                fm_start: start,
+                fm_length: u64::MAX - start,
+                fm_flags: 0,
+                fm_mapped_extents: 0,
+                fm_extent_count: BATCH as u32,
+                fm_reserved: 0,
+                fm_extents: [FiemapExtent::default(); BATCH],
+            };
+            // SAFETY: `map` is a valid `struct fiemap` with room for `fm_extent_count` extents.
+            if unsafe { libc::ioctl(file.as_raw_fd(), FS_IOC_FIEMAP as _, &mut map) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let extents = &map.fm_extents[..map.fm_mapped_extents as usize];
+            extents.iter().for_each(&mut f);
+            match extents.last() {
+                Some(last) if last.fe_flags & FIEMAP_EXTENT_LAST == 0 => {
+                    start = last.fe_logical + last.fe_length;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod apfs {
+    use std::{fs::File, io, mem, os::fd::AsRawFd};
+
+    /// Extended common attributes requested from `getattrlist`.
+    const REQUESTED: libc::attrgroup_t = libc::ATTR_CMNEXT_PRIVATESIZE | libc::ATTR_CMNEXT_CLONEID;
+
+    pub(super) struct CloneInfo {
+        /// Bytes allocated to the file and not shared with any other file.
+        pub(super) private_size: u64,
+        /// Identifier of the data of the file, shared by files that are pure clones of each other.
+        pub(super) clone_id: u64,
+    }
+
+    /// The attributes returned for [`REQUESTED`], packed in the order of their bits.
+    #[repr(C, packed(4))]
+    struct Attributes {
+        length: u32,
+        returned: libc::attribute_set_t,
+        private_size: libc::off_t,
+        clone_id: u64,
+    }
+
+    /// Get the clone accounting of `file`, which only APFS provides.
+    pub(super) fn clone_info(file: &File) -> io::Result<CloneInfo> {
+        let mut request = libc::attrlist {
+            bitmapcount: libc::ATTR_BIT_MAP_COUNT,
+            reserved: 0,
+            commonattr: libc::ATTR_CMN_RETURNED_ATTRS,
+            volattr: 0,
+            dirattr: 0,
+            fileattr: 0,
+            forkattr: REQUESTED,
+        };
+        // SAFETY: `Attributes` only holds integers, for which zero is a valid value.
+        let mut attributes: Attributes = unsafe { mem::zeroed() };
+        // SAFETY: `request` is a valid `struct attrlist`, and `attributes` has room
+        // for the requested attributes, as its size tells.
+        let result = unsafe {
+            libc::fgetattrlist(
+                file.as_raw_fd(),
+                (&mut request as *mut libc::attrlist).cast(),
+                (&mut attributes as *mut Attributes).cast(),
+                mem::size_of::<Attributes>(),
+                libc::FSOPT_ATTR_CMN_EXTENDED,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Other filesystems leave out the attributes they do not support.
+        let returned = attributes.returned;
+        if returned.forkattr & REQUESTED != REQUESTED {
+            return Err(io::ErrorKind::Unsupported.into());
+        }
+        Ok(CloneInfo {
+            private_size: u64::try_from(attributes.private_size).unwrap_or(0),
+            clone_id: attributes.clone_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_get_physical_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        fs::write(dir.path().join("a"), &data).unwrap();
+        fs::write(dir.path().join("b"), &data).unwrap();
+
+        let a = get_physical_size(&dir.path().join("a")).unwrap();
+        let b = get_physical_size(&dir.path().join("b")).unwrap();
+        assert!(a >= 100_000);
+        assert!(b >= 100_000);
+        // Plain copies share nothing, so they add up.
+        assert_eq!(get_physical_size(dir.path()).unwrap(), a + b);
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_get_physical_size_of_clones() {
+        use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        fs::write(dir.path().join("a"), &data).unwrap();
+        let path = |name| CString::new(dir.path().join(name).as_os_str().as_bytes()).unwrap();
+        // SAFETY: both paths are valid C strings.
+        if unsafe { libc::clonefile(path("a").as_ptr(), path("clone").as_ptr(), 0) } < 0 {
+            // Not on APFS.
+            return;
+        }
+
+        let a = get_physical_size(&dir.path().join("a")).unwrap();
+        assert!(a >= 100_000);
+        assert_eq!(get_physical_size(&dir.path().join("clone")).unwrap(), a);
+        // The clone shares its data with the original, which is only counted once.
+        let total = get_physical_size(dir.path()).unwrap();
+        assert!(total >= a && total < 2 * a);
+    }
+}
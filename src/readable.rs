@@ -0,0 +1,81 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{io, path::Path};
+
+/// Check that the current process can open the file at `path` for reading.
+///
+/// This asks the kernel with `faccessat`, using the effective user and group IDs
+/// like opening the file would, without opening it.
+#[cfg(target_os = "linux")]
+pub(crate) fn check_readable(path: &Path) -> io::Result<()> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: `c_path` is a valid NUL-terminated path.
+    let result = unsafe {
+        libc::faccessat(
+            libc::AT_FDCWD,
+            c_path.as_ptr(),
+            libc::R_OK,
+            libc::AT_EACCESS,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Check that the current process can open the file at `path` for reading.
+///
+/// The file is briefly opened for reading and closed again, which also accounts
+/// for ACLs and other access controls.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn check_readable(path: &Path) -> io::Result<()> {
+    std::fs::File::open(path).map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_size_with_options, test_util, ErrorPolicy, SizeOptions};
+    use std::fs;
+
+    #[test]
+    fn test_readable_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let locked = dir.path().join("locked");
+        fs::write(dir.path().join("file"), [0; 100]).unwrap();
+        fs::write(&locked, [0; 10]).unwrap();
+        assert!(check_readable(&dir.path().join("file")).is_ok());
+        assert!(check_readable(&dir.path().join("missing")).is_err());
+
+        let Some(_locked) = test_util::lock(&locked) else {
+            return;
+        };
+        assert_eq!(
+            check_readable(&locked).unwrap_err().kind(),
+            io::ErrorKind::PermissionDenied
+        );
+
+        let size = |options: SizeOptions| get_size_with_options(dir.path(), &options);
+        assert_eq!(size(SizeOptions::new()).unwrap().bytes, 110);
+        assert_eq!(
+            size(SizeOptions::new().readable_only(true)).unwrap().bytes,
+            100
+        );
+
+        let options = SizeOptions::new()
+            .readable_only(true)
+            .error_policy(ErrorPolicy::Collect);
+        let report = size(options).unwrap();
+        assert_eq!(report.bytes, 100);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, locked);
+
+        let options = SizeOptions::new().readable_only(true);
+        assert!(get_size_with_options(&locked, &options).is_err());
+    }
+}
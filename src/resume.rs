@@ -0,0 +1,198 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    walk::{normalize, Visitor, Walker},
+    Error, SizeOptions,
+};
+use std::{
+    collections::HashSet,
+    fs::{self, Metadata},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Get the size (in bytes) of the part of the file not sized yet, skipping the directories in `visited`.
+///
+/// Works like [`resume_size_with`] with the default options.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn resume_size(path: &Path, visited: &mut HashSet<PathBuf>) -> Result<u64, Error> {
+    resume_size_with(path, visited, &SizeOptions::new())
+}
+
+/// Get the size (in bytes) of the part of the file not sized yet, walking it according to `options`.
+///
+/// The directories in `visited` and everything below them are skipped, as their size is assumed
+/// to be known from a previous call, and so is `path` itself if it is in `visited` or below one
+/// of its directories. Once the rest is walked, `path` and every directory completely walked
+/// below it are added to `visited`, so the returned sizes of successive calls with the same set
+/// add up to the size of the tree, whichever parts of it they are called on, and a later call
+/// on `path` or anything below it returns 0. Paths are recorded normalized, see the
+/// [crate documentation](crate#paths), and skipped directories must be spelled as the walk finds
+/// them, i.e. starting with the normalized walked path.
+///
+/// This supports checkpointed scans of very large trees, which are sized one part at a time,
+/// e.g. one subdirectory per call, with `visited` persisted between calls: a scan stopped
+/// between two calls resumes where it stopped, and a call on a parent of the parts already
+/// sized only walks what remains. If a call fails, `visited` is left unchanged. The result is
+/// only exact if the skipped subtrees did not change since they were sized; otherwise, it is
+/// an approximation, which a scan with an empty set corrects.
+///
+/// Memory grows with the number of directories walked by the call.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`,
+/// or if [`SizeOptions::max_entries`] is set, since it leaves directories incomplete.
+pub fn resume_size_with(
+    path: &Path,
+    visited: &mut HashSet<PathBuf>,
+    options: &SizeOptions,
+) -> Result<u64, Error> {
+    if options.max_entries.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "a resumable scan cannot be limited to a maximum number of entries",
+        )
+        .into());
+    }
+    let root = normalize(path);
+    if is_visited(visited, &root) {
+        fs::symlink_metadata(&root)?;
+        return Ok(0);
+    }
+
+    let (bytes, completed) = Walker::new(options).run(&root, &Resume { visited })?;
+    visited.extend(completed);
+    visited.insert(root);
+    Ok(bytes)
+}
+
+/// Whether `path` was sized before, on its own or as part of one of its ancestors.
+fn is_visited(visited: &HashSet<PathBuf>, path: &Path) -> bool {
+    path.ancestors().any(|ancestor| visited.contains(ancestor))
+}
+
+struct Resume<'a> {
+    visited: &'a HashSet<PathBuf>,
+}
+
+impl Visitor for Resume<'_> {
+    /// Size of the subtree, and the directories completely walked in it.
+    type Acc = (u64, Vec<PathBuf>);
+
+    fn init(&self) -> Self::Acc {
+        (0, Vec::new())
+    }
+
+    fn visit_file(&self, acc: &mut Self::Acc, _path: &Path, _meta: &Metadata, size: u64) {
+        acc.0 += size;
+    }
+
+    fn enter_dir(&self, path: &Path) -> bool {
+        !is_visited(self.visited, path)
+    }
+
+    fn leave_dir(&self, acc: &mut Self::Acc, path: &Path, _meta: &Metadata) {
+        acc.1.push(path.to_path_buf());
+    }
+
+    fn merge(&self, (a_size, mut a_dirs): Self::Acc, (b_size, b_dirs): Self::Acc) -> Self::Acc {
+        a_dirs.extend(b_dirs);
+        (a_size + b_size, a_dirs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/deep")).unwrap();
+        fs::create_dir(dir.path().join("b")).unwrap();
+        for (name, size) in [
+            ("root", 1),
+            ("a/file", 10),
+            ("a/deep/file", 100),
+            ("b/file", 1000),
+        ] {
+            fs::write(dir.path().join(name), vec![0; size]).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_resume_size() {
+        let dir = tree();
+
+        let mut visited = HashSet::from([dir.path().join("a")]);
+        assert_eq!(resume_size(dir.path(), &mut visited).unwrap(), 1001);
+        assert!(visited.contains(dir.path()));
+        assert_eq!(resume_size(dir.path(), &mut visited).unwrap(), 0);
+
+        let mut visited = HashSet::new();
+        assert_eq!(resume_size(dir.path(), &mut visited).unwrap(), 1111);
+        assert_eq!(
+            visited,
+            HashSet::from([
+                dir.path().to_path_buf(),
+                dir.path().join("a"),
+                dir.path().join("a/deep"),
+                dir.path().join("b"),
+            ])
+        );
+
+        let file = dir.path().join("root");
+        let mut visited = HashSet::new();
+        assert_eq!(resume_size(&file, &mut visited).unwrap(), 1);
+        assert_eq!(resume_size(&file, &mut visited).unwrap(), 0);
+
+        assert!(resume_size(&dir.path().join("missing"), &mut HashSet::new()).is_err());
+        let options = SizeOptions::new().max_entries(10);
+        assert!(resume_size_with(dir.path(), &mut HashSet::new(), &options).is_err());
+    }
+
+    #[test]
+    fn test_resume_in_chunks() {
+        let dir = tree();
+
+        let mut visited = HashSet::new();
+        assert_eq!(
+            resume_size(&dir.path().join("a"), &mut visited).unwrap(),
+            110
+        );
+        assert_eq!(
+            resume_size(&dir.path().join("b"), &mut visited).unwrap(),
+            1000
+        );
+        assert!(resume_size(&dir.path().join("missing"), &mut visited).is_err());
+        assert_eq!(visited.len(), 3);
+        assert!(visited.contains(&dir.path().join("a/deep")));
+        assert_eq!(resume_size(dir.path(), &mut visited).unwrap(), 1);
+        assert!(visited.contains(dir.path()));
+    }
+
+    #[test]
+    fn test_resume_parent_then_child() {
+        let dir = tree();
+
+        let mut visited = HashSet::new();
+        assert_eq!(resume_size(dir.path(), &mut visited).unwrap(), 1111);
+        // Everything below the parent was already counted with it.
+        assert_eq!(resume_size(&dir.path().join("a"), &mut visited).unwrap(), 0);
+        assert_eq!(
+            resume_size(&dir.path().join("a/deep/file"), &mut visited).unwrap(),
+            0
+        );
+
+        let mut visited = HashSet::from([dir.path().join("a")]);
+        assert_eq!(
+            resume_size(&dir.path().join("a/deep"), &mut visited).unwrap(),
+            0
+        );
+        assert_eq!(resume_size(dir.path(), &mut visited).unwrap(), 1001);
+    }
+}
@@ -0,0 +1,127 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::get_size_in_bytes;
+use rayon::prelude::*;
+use std::{io, path::PathBuf};
+
+/// The sizes of several roots scanned together, see [`get_roots_breakdown`].
+///
+/// With the `serde` feature, it can be serialized, with each root as an object holding
+/// its `path` and either its `bytes` or its `error` message, and the `grand_total`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct RootsReport {
+    /// Each root with its size (in bytes), or the error that prevented calculating it,
+    /// in the order the roots were given.
+    pub per_root: Vec<(PathBuf, io::Result<u64>)>,
+    /// Sum of the sizes of the roots that were calculated successfully.
+    pub grand_total: u64,
+}
+
+/// Get the size of each of `roots` (in bytes), along with their total.
+///
+/// The roots are calculated in parallel, each like [`get_size_in_bytes`] does.
+/// A root that fails does not prevent the others from being calculated: its error is
+/// kept in [`RootsReport::per_root`], and it does not count towards the grand total.
+/// Roots nested in one another are calculated independently, so their shared files
+/// count towards the grand total once per root.
+///
+/// Memory grows with the number of roots.
+pub fn get_roots_breakdown(roots: &[PathBuf]) -> RootsReport {
+    let per_root: Vec<_> = roots
+        .par_iter()
+        .map(|root| (root.clone(), get_size_in_bytes(root)))
+        .collect();
+    let grand_total = per_root
+        .iter()
+        .filter_map(|(_, size)| size.as_ref().ok())
+        .sum();
+    RootsReport {
+        per_root,
+        grand_total,
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for RootsReport {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        #[derive(serde::Serialize)]
+        struct Root<'a> {
+            path: &'a std::path::Path,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            bytes: Option<u64>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            error: Option<String>,
+        }
+
+        let per_root: Vec<_> = self
+            .per_root
+            .iter()
+            .map(|(path, size)| Root {
+                path,
+                bytes: size.as_ref().ok().copied(),
+                error: size.as_ref().err().map(ToString::to_string),
+            })
+            .collect();
+        let mut report = serializer.serialize_struct("RootsReport", 2)?;
+        report.serialize_field("per_root", &per_root)?;
+        report.serialize_field("grand_total", &self.grand_total)?;
+        report.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_get_roots_breakdown() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("a")).unwrap();
+        fs::write(dir.path().join("a/file"), [0; 100]).unwrap();
+        fs::write(dir.path().join("b"), [0; 200]).unwrap();
+
+        let roots = [
+            dir.path().join("a"),
+            dir.path().join("missing"),
+            dir.path().join("b"),
+        ];
+        let report = get_roots_breakdown(&roots);
+        assert_eq!(report.grand_total, 300);
+        assert_eq!(report.per_root.len(), 3);
+        for ((path, _), root) in report.per_root.iter().zip(&roots) {
+            assert_eq!(path, root);
+        }
+        assert_eq!(*report.per_root[0].1.as_ref().unwrap(), 100);
+        assert_eq!(
+            report.per_root[1].1.as_ref().unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(*report.per_root[2].1.as_ref().unwrap(), 200);
+
+        let report = get_roots_breakdown(&[]);
+        assert!(report.per_root.is_empty());
+        assert_eq!(report.grand_total, 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let report = RootsReport {
+            per_root: vec![
+                ("a".into(), Ok(100)),
+                ("b".into(), Err(io::Error::other("denied"))),
+            ],
+            grand_total: 100,
+        };
+        assert_eq!(
+            serde_json::to_string(&report).unwrap(),
+            r#"{"per_root":[{"path":"a","bytes":100},{"path":"b","error":"denied"}],"grand_total":100}"#
+        );
+    }
+}
@@ -0,0 +1,119 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    hash::{fnv1a, hash_path, mix},
+    walk::{normalize, walk, Visitor},
+};
+use std::{
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Get a hash of the structure of the file, for cheap change detection.
+///
+/// The signature combines the path relative to `path`, the size and the modification time
+/// of every file, so that adding, removing, renaming, resizing or touching a file changes it,
+/// while the location of the tree does not: a copy preserving modification times has the
+/// same signature. The hash is stable between runs and releases of this crate on a given
+/// platform, so signatures can be persisted and compared later.
+///
+/// This is a heuristic, not a hash of the contents: a file rewritten with the same size
+/// within the resolution of the filesystem's modification times, or whose modification
+/// time was restored, goes unnoticed. Equal signatures strongly suggest that nothing
+/// changed, and different ones that something did.
+///
+/// Each file is hashed on its own and the hashes are combined regardless of order,
+/// so this runs in bounded memory.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn structure_signature(path: &Path) -> io::Result<u64> {
+    let (sum, files) = walk(
+        path,
+        &Signature {
+            root: normalize(path),
+        },
+    )?;
+    Ok(mix(sum ^ mix(files)))
+}
+
+struct Signature {
+    root: PathBuf,
+}
+
+impl Visitor for Signature {
+    /// Wrapping sum of the hashes of the files, and their number.
+    type Acc = (u64, u64);
+
+    fn init(&self) -> Self::Acc {
+        (0, 0)
+    }
+
+    fn visit_file(&self, acc: &mut Self::Acc, path: &Path, meta: &Metadata, size: u64) {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let mut hash = fnv1a(hash_path(0, relative), &size.to_le_bytes());
+        hash = match meta.modified().map(|time| time.duration_since(UNIX_EPOCH)) {
+            Ok(Ok(after)) => fnv1a(hash ^ 1, &after.as_nanos().to_le_bytes()),
+            Ok(Err(before)) => fnv1a(hash ^ 2, &before.duration().as_nanos().to_le_bytes()),
+            Err(_) => hash,
+        };
+        acc.0 = acc.0.wrapping_add(mix(hash));
+        acc.1 += 1;
+    }
+
+    fn merge(&self, a: Self::Acc, b: Self::Acc) -> Self::Acc {
+        (a.0.wrapping_add(b.0), a.1 + b.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        fs::{self, File},
+        time::Duration,
+    };
+
+    fn tree(root: &Path) {
+        fs::create_dir(root.join("sub")).unwrap();
+        for (name, size) in [("a", 10), ("sub/b", 20)] {
+            let file = File::create(root.join(name)).unwrap();
+            file.set_len(size).unwrap();
+            file.set_modified(UNIX_EPOCH + Duration::from_secs(1_000_000))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_structure_signature() {
+        let (first, second) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        tree(first.path());
+        tree(second.path());
+        let signature = structure_signature(first.path()).unwrap();
+        assert_eq!(structure_signature(second.path()).unwrap(), signature);
+        assert_eq!(structure_signature(first.path()).unwrap(), signature);
+
+        let file = File::options()
+            .write(true)
+            .open(second.path().join("sub/b"))
+            .unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(2_000_000))
+            .unwrap();
+        let touched = structure_signature(second.path()).unwrap();
+        assert_ne!(touched, signature);
+
+        file.set_len(21).unwrap();
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(1_000_000))
+            .unwrap();
+        let resized = structure_signature(second.path()).unwrap();
+        assert_ne!(resized, signature);
+        assert_ne!(resized, touched);
+
+        fs::rename(first.path().join("a"), first.path().join("c")).unwrap();
+        assert_ne!(structure_signature(first.path()).unwrap(), signature);
+    }
+}
@@ -0,0 +1,78 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+};
+use tempfile::TempDir;
+
+/// A path whose permissions were removed, restored when dropped so the tree can be cleaned up.
+pub(crate) struct Locked {
+    path: PathBuf,
+    permissions: fs::Permissions,
+}
+
+impl Drop for Locked {
+    fn drop(&mut self) {
+        let _ = fs::set_permissions(&self.path, self.permissions.clone());
+    }
+}
+
+/// Remove every permission from `path`.
+///
+/// Returns `None` where permissions are not enforced (e.g. not on Unix, or when running as root),
+/// after telling the test is skipped, so tests relying on it can return early.
+pub(crate) fn lock(path: &Path) -> Option<Locked> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let locked = Locked {
+            path: path.to_path_buf(),
+            permissions: fs::symlink_metadata(path).unwrap().permissions(),
+        };
+        fs::set_permissions(path, fs::Permissions::from_mode(0o000)).unwrap();
+        if fs::File::open(path).is_err() {
+            return Some(locked);
+        }
+    }
+
+    let _ = path;
+    eprintln!(
+        "skipping {}: permissions are not enforced",
+        thread::current().name().unwrap_or("test")
+    );
+    None
+}
+
+/// A tree holding a 100-byte `file` and an unreadable `locked` directory, see [`tree_with_unreadable_dir`].
+pub(crate) struct UnreadableTree {
+    // Declared first to be dropped first, so the directory can be removed afterwards.
+    _locked: Locked,
+    dir: TempDir,
+}
+
+impl UnreadableTree {
+    pub(crate) fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+/// Create a tree holding a 100-byte file and an unreadable `locked` directory holding another.
+///
+/// Returns `None` where permissions are not enforced, see [`lock`].
+pub(crate) fn tree_with_unreadable_dir() -> Option<UnreadableTree> {
+    let dir = tempfile::tempdir().unwrap();
+    let locked = dir.path().join("locked");
+    fs::write(dir.path().join("file"), [0; 100]).unwrap();
+    fs::create_dir(&locked).unwrap();
+    fs::write(locked.join("file"), [0; 100]).unwrap();
+
+    Some(UnreadableTree {
+        _locked: lock(&locked)?,
+        dir,
+    })
+}
@@ -0,0 +1,92 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+/// Keeps the `limit` greatest items pushed into it, in `O(limit)` memory.
+pub(crate) struct TopN<T> {
+    limit: usize,
+    heap: BinaryHeap<Reverse<T>>,
+}
+
+impl<T: Ord> TopN<T> {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    /// Whether `item` would currently be kept, allowing callers to skip building it.
+    pub(crate) fn accepts(&self, item: &T) -> bool {
+        match self.heap.peek() {
+            _ if self.limit == 0 => false,
+            Some(Reverse(min)) if self.heap.len() == self.limit => item > min,
+            _ => true,
+        }
+    }
+
+    pub(crate) fn push(&mut self, item: T) {
+        if self.accepts(&item) {
+            if self.heap.len() == self.limit {
+                self.heap.pop();
+            }
+            self.heap.push(Reverse(item));
+        }
+    }
+
+    /// The smallest item kept so far, once `limit` items are kept.
+    pub(crate) fn threshold(&self) -> Option<&T> {
+        match self.heap.peek() {
+            Some(Reverse(min)) if self.heap.len() == self.limit => Some(min),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn merge(self, other: Self) -> Self {
+        let (mut larger, smaller) = if self.heap.len() >= other.heap.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        for Reverse(item) in smaller.heap {
+            larger.push(item);
+        }
+        larger
+    }
+
+    /// The kept items, greatest first.
+    pub(crate) fn into_sorted_vec(self) -> Vec<T> {
+        // Sorting `Reverse` ascending yields the items in descending order.
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(item)| item)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_n() {
+        let mut a = TopN::new(3);
+        let mut b = TopN::new(3);
+        for i in [5, 1, 9, 3] {
+            a.push(i);
+        }
+        for i in [7, 2, 8] {
+            b.push(i);
+        }
+        assert_eq!(a.threshold(), Some(&3));
+        assert!(!a.accepts(&2));
+        assert_eq!(a.merge(b).into_sorted_vec(), [9, 8, 7]);
+
+        let mut empty = TopN::new(0);
+        empty.push(1);
+        assert!(empty.into_sorted_vec().is_empty());
+    }
+}
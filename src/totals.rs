@@ -0,0 +1,155 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::walk::{walk, Visitor};
+use std::{
+    fs::Metadata,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, SyncSender},
+    },
+    thread,
+};
+
+/// Number of directory totals buffered before the walk waits for them to be consumed.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Iterate over every directory with its recursive size (in bytes), as the walk completes them.
+///
+/// The walk runs in a background thread, and each directory is yielded once its whole
+/// subtree has been walked, so directories always come after all of their subdirectories
+/// (post-order) and `path` itself comes last. The order of sibling directories is unspecified,
+/// since they are walked in parallel. If `path` is not a directory, nothing is yielded.
+///
+/// At most a fixed number of totals is buffered, so the walk runs in bounded memory
+/// even if the iterator is consumed slowly. Dropping the iterator stops the walk.
+/// The walk runs on a thread pool of its own, which waits for the totals to be consumed
+/// without holding up the global rayon pool, so the consumer can use it freely.
+///
+/// The iterator will yield a single error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn walk_dir_totals(path: &Path) -> impl Iterator<Item = io::Result<(PathBuf, u64)>> {
+    let path = path.to_path_buf();
+    let (sender, receiver) = mpsc::sync_channel(CHANNEL_CAPACITY);
+    thread::spawn(move || {
+        let visitor = DirTotals {
+            sender,
+            disconnected: AtomicBool::new(false),
+        };
+        // Workers wait in `leave_dir` while the buffer is full, so they must not be
+        // those of the global pool, which the consumer may need to make progress.
+        let result = rayon::ThreadPoolBuilder::new()
+            .build()
+            .map_err(io::Error::other)
+            .and_then(|pool| pool.install(|| walk(&path, &visitor)));
+        if let Err(e) = result {
+            let _ = visitor.sender.send(Err(e));
+        }
+    });
+    receiver.into_iter()
+}
+
+struct DirTotals {
+    sender: SyncSender<io::Result<(PathBuf, u64)>>,
+    /// Set once the iterator is dropped, so the rest of the walk can be skipped.
+    disconnected: AtomicBool,
+}
+
+impl Visitor for DirTotals {
+    type Acc = u64;
+
+    fn init(&self) -> u64 {
+        0
+    }
+
+    fn visit_file(&self, acc: &mut u64, _path: &Path, _meta: &Metadata, size: u64) {
+        *acc += size;
+    }
+
+    fn leave_dir(&self, acc: &mut u64, path: &Path, _meta: &Metadata) {
+        if self.sender.send(Ok((path.to_path_buf(), *acc))).is_err() {
+            self.disconnected.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn merge(&self, a: u64, b: u64) -> u64 {
+        a + b
+    }
+
+    fn is_done(&self) -> bool {
+        self.disconnected.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashMap, fs};
+
+    #[test]
+    fn test_walk_dir_totals() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::create_dir(dir.path().join("c")).unwrap();
+        fs::write(dir.path().join("root"), [0; 1]).unwrap();
+        fs::write(dir.path().join("a/a"), [0; 10]).unwrap();
+        fs::write(dir.path().join("a/b/b"), [0; 100]).unwrap();
+
+        let totals: Vec<_> = walk_dir_totals(dir.path())
+            .collect::<io::Result<_>>()
+            .unwrap();
+        let position = |path: &Path| totals.iter().position(|(p, _)| p == path).unwrap();
+        assert!(position(&dir.path().join("a/b")) < position(&dir.path().join("a")));
+        assert_eq!(position(dir.path()), totals.len() - 1);
+        assert_eq!(
+            totals.into_iter().collect::<HashMap<_, _>>(),
+            HashMap::from([
+                (dir.path().to_path_buf(), 111),
+                (dir.path().join("a"), 110),
+                (dir.path().join("a/b"), 100),
+                (dir.path().join("c"), 0),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_walk_dir_totals_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut totals = walk_dir_totals(&dir.path().join("missing"));
+        assert_eq!(
+            totals.next().unwrap().unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert!(totals.next().is_none());
+
+        fs::write(dir.path().join("file"), [0; 10]).unwrap();
+        assert_eq!(walk_dir_totals(&dir.path().join("file")).count(), 0);
+    }
+
+    #[test]
+    fn test_walk_dir_totals_with_rayon_consumer() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..CHANNEL_CAPACITY * 4 {
+            fs::create_dir(dir.path().join(i.to_string())).unwrap();
+            fs::write(dir.path().join(format!("{i}/a")), [0; 1]).unwrap();
+            fs::write(dir.path().join(format!("{i}/b")), [0; 1]).unwrap();
+        }
+        // Sizing a directory of several entries again goes through the global rayon pool.
+        for total in walk_dir_totals(dir.path()) {
+            let (path, size) = total.unwrap();
+            assert_eq!(crate::get_size_in_bytes(&path).unwrap(), size);
+        }
+    }
+
+    #[test]
+    fn test_walk_dir_totals_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..CHANNEL_CAPACITY * 4 {
+            fs::create_dir(dir.path().join(i.to_string())).unwrap();
+        }
+        assert_eq!(walk_dir_totals(dir.path()).take(3).count(), 3);
+    }
+}
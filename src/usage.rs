@@ -0,0 +1,196 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{
+    walk::{walk, Visitor, Walker},
+    Error, SizeOptions,
+};
+use std::{fs::Metadata, io, path::Path};
+
+/// Disk usage along the two dimensions quotas are usually enforced on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Usage {
+    /// Total size (in bytes).
+    pub bytes: u64,
+    /// Number of entries, including directories, symlinks and `path` itself.
+    pub inodes: u64,
+}
+
+/// Get the size (in bytes) and the number of entries of the file in a single walk.
+///
+/// If `path` points to a directory, all of its files and subdirectories are included.
+/// Every entry counts as one inode, including directories, symlinks and special files,
+/// but entries that could not be accessed are skipped.
+/// Runs in bounded memory.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_usage(path: &Path) -> io::Result<Usage> {
+    Ok(get_usage_with(path, &SizeOptions::new())?)
+}
+
+/// Get the size (in bytes) and the number of entries of the file like [`get_usage`], walking it according to `options`.
+///
+/// Sizes are measured as set by [`SizeOptions::size_kind`], and entries skipped by the
+/// options are neither counted as bytes nor as inodes.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_usage_with(path: &Path, options: &SizeOptions) -> Result<Usage, Error> {
+    Ok(Walker::new(options).run(path, &UsageVisitor)?)
+}
+
+/// The size of a tree split between the data of its files and the space taken by its directories.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataAndOverhead {
+    /// Total size of the files (in bytes), as counted by [`get_size_in_bytes`](crate::get_size_in_bytes).
+    pub file_data_bytes: u64,
+    /// Total size of the directories themselves (in bytes), including `path`.
+    pub directory_overhead_bytes: u64,
+}
+
+/// Get the size of the files (in bytes) and the size of the directories holding them in a single walk.
+///
+/// The size of a directory is the length reported by its metadata, which is the space its
+/// entries take on most Unix filesystems (e.g. a multiple of 4096 bytes on ext4), but is
+/// always 0 on others, including Windows. Neither includes the size of the other, and
+/// directories that cannot be read are skipped altogether.
+/// Runs in bounded memory.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`.
+pub fn get_data_and_overhead(path: &Path) -> io::Result<DataAndOverhead> {
+    walk(path, &DataAndOverheadVisitor)
+}
+
+struct UsageVisitor;
+
+impl Visitor for UsageVisitor {
+    type Acc = Usage;
+
+    fn init(&self) -> Usage {
+        Usage::default()
+    }
+
+    fn visit_file(&self, acc: &mut Usage, _path: &Path, _meta: &Metadata, size: u64) {
+        acc.bytes += size;
+        acc.inodes += 1;
+    }
+
+    fn visit_uncounted(&self, acc: &mut Usage, _path: &Path, _meta: &Metadata) {
+        acc.inodes += 1;
+    }
+
+    fn leave_dir(&self, acc: &mut Usage, _path: &Path, _meta: &Metadata) {
+        acc.inodes += 1;
+    }
+
+    fn merge(&self, a: Usage, b: Usage) -> Usage {
+        Usage {
+            bytes: a.bytes + b.bytes,
+            inodes: a.inodes + b.inodes,
+        }
+    }
+}
+
+struct DataAndOverheadVisitor;
+
+impl Visitor for DataAndOverheadVisitor {
+    type Acc = DataAndOverhead;
+
+    fn init(&self) -> DataAndOverhead {
+        DataAndOverhead::default()
+    }
+
+    fn visit_file(&self, acc: &mut DataAndOverhead, _path: &Path, _meta: &Metadata, size: u64) {
+        acc.file_data_bytes += size;
+    }
+
+    fn leave_dir(&self, acc: &mut DataAndOverhead, _path: &Path, meta: &Metadata) {
+        acc.directory_overhead_bytes += meta.len();
+    }
+
+    fn merge(&self, a: DataAndOverhead, b: DataAndOverhead) -> DataAndOverhead {
+        DataAndOverhead {
+            file_data_bytes: a.file_data_bytes + b.file_data_bytes,
+            directory_overhead_bytes: a.directory_overhead_bytes + b.directory_overhead_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_get_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::create_dir(dir.path().join("empty")).unwrap();
+        fs::write(dir.path().join("file"), [0; 100]).unwrap();
+        fs::write(dir.path().join("a/file"), [0; 200]).unwrap();
+        fs::write(dir.path().join("a/b/file"), [0; 300]).unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("file", dir.path().join("a/link")).unwrap();
+
+        // The root, `a`, `a/b`, `empty` and three files, plus the symlink on Unix.
+        let inodes = if cfg!(unix) { 8 } else { 7 };
+        assert_eq!(get_usage(dir.path()).unwrap(), Usage { bytes: 600, inodes });
+        assert_eq!(
+            get_usage(&dir.path().join("file")).unwrap(),
+            Usage {
+                bytes: 100,
+                inodes: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_data_and_overhead() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        fs::write(dir.path().join("file"), [0; 100]).unwrap();
+        fs::write(dir.path().join("a/b/file"), [0; 200]).unwrap();
+
+        let overhead: u64 = [dir.path(), &dir.path().join("a"), &dir.path().join("a/b")]
+            .iter()
+            .map(|path| fs::metadata(path).unwrap().len())
+            .sum();
+        assert_eq!(
+            get_data_and_overhead(dir.path()).unwrap(),
+            DataAndOverhead {
+                file_data_bytes: 300,
+                directory_overhead_bytes: overhead
+            }
+        );
+        assert_eq!(
+            get_data_and_overhead(&dir.path().join("file")).unwrap(),
+            DataAndOverhead {
+                file_data_bytes: 100,
+                directory_overhead_bytes: 0
+            }
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let split = DataAndOverhead {
+            file_data_bytes: 300,
+            directory_overhead_bytes: 4096,
+        };
+        let json = serde_json::to_string(&split).unwrap();
+        assert_eq!(
+            json,
+            r#"{"file_data_bytes":300,"directory_overhead_bytes":4096}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<DataAndOverhead>(&json).unwrap(),
+            split
+        );
+    }
+}
@@ -0,0 +1,526 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::{physical::SharedExtents, ErrorPolicy, SizeKind, SizeOptions};
+use rayon::prelude::*;
+use std::{
+    fs::{self, Metadata},
+    io, iter,
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+/// Accumulates data about the entries found during a parallel walk.
+///
+/// Each rayon worker folds the entries it processes into its own accumulator,
+/// and accumulators are merged pairwise as the work is joined back together,
+/// so there is never a serial merge over every entry at the end.
+pub(crate) trait Visitor: Sync {
+    type Acc: Send;
+
+    /// Create an empty accumulator.
+    fn init(&self) -> Self::Acc;
+
+    /// Record a counted non-directory entry, which is `size` bytes large.
+    fn visit_file(&self, acc: &mut Self::Acc, path: &Path, meta: &Metadata, size: u64);
+
+    /// Record a non-directory entry that does not count towards the size,
+    /// such as a symlink or a special file.
+    fn visit_uncounted(&self, _acc: &mut Self::Acc, _path: &Path, _meta: &Metadata) {}
+
+    /// Whether to walk the directory at `path`, which is otherwise skipped like the directories
+    /// rejected by [`SizeOptions::descend_into`].
+    fn enter_dir(&self, _path: &Path) -> bool {
+        true
+    }
+
+    /// Record a directory once its whole subtree has been folded into `acc`.
+    fn leave_dir(&self, _acc: &mut Self::Acc, _path: &Path, _meta: &Metadata) {}
+
+    /// Combine the accumulators of two disjoint parts of the tree.
+    fn merge(&self, a: Self::Acc, b: Self::Acc) -> Self::Acc;
+
+    /// Whether the visitor has seen enough, so the remaining entries can be skipped.
+    fn is_done(&self) -> bool {
+        false
+    }
+}
+
+/// Walk `path` recursively with the default options, feeding every counted entry to `visitor`.
+pub(crate) fn walk<V: Visitor>(path: &Path, visitor: &V) -> io::Result<V::Acc> {
+    Walker::new(&SizeOptions::default()).run(path, visitor)
+}
+
+/// Normalize `path` lexically, so that every spelling of the same path is walked alike.
+///
+/// Trailing and repeated separators and `.` components are dropped, so `dir/`, `dir/.`
+/// and `./dir` are all walked as `dir`, and a path made only of `.` components as `.`.
+/// `..` components are kept, since resolving them lexically would be wrong across symlinks.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let normalized: PathBuf = path
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .collect();
+    if normalized.as_os_str().is_empty() && !path.as_os_str().is_empty() {
+        return PathBuf::from(".");
+    }
+    normalized
+}
+
+/// A single walk over a tree, holding the state shared between worker threads.
+pub(crate) struct Walker<'a> {
+    options: &'a SizeOptions,
+    errors: Mutex<Vec<(PathBuf, io::Error)>>,
+    /// Number of entries that vanished between being listed and being accessed.
+    races: AtomicU64,
+    /// Number of entries visited so far, only tracked with a maximum number of entries.
+    entries: AtomicUsize,
+    /// Whether entries were skipped after reaching the maximum number of entries.
+    truncated: AtomicBool,
+    /// Shared extents already counted, with [`SizeKind::Physical`].
+    shared_extents: SharedExtents,
+    /// Device of the walked path, recorded when staying on its filesystem.
+    #[cfg(unix)]
+    root_device: OnceLock<u64>,
+    /// Canonical walked path, recorded when following the symlinks within it.
+    canonical_root: OnceLock<PathBuf>,
+}
+
+/// The canonical location of a directory being walked, linked to those of its ancestors.
+///
+/// Only tracked when following symlinks, to detect the ones leading back into an ancestor.
+struct RealDir<'p> {
+    path: PathBuf,
+    parent: Option<&'p RealDir<'p>>,
+}
+
+impl RealDir<'_> {
+    /// Whether `target` is this directory, one of its ancestors, or an ancestor of them.
+    fn is_within(&self, target: &Path) -> bool {
+        iter::successors(Some(self), |dir| dir.parent).any(|dir| dir.path.starts_with(target))
+    }
+}
+
+impl<'a> Walker<'a> {
+    pub(crate) fn new(options: &'a SizeOptions) -> Self {
+        Self {
+            options,
+            errors: Mutex::new(Vec::new()),
+            races: AtomicU64::new(0),
+            entries: AtomicUsize::new(0),
+            truncated: AtomicBool::new(false),
+            shared_extents: SharedExtents::default(),
+            #[cfg(unix)]
+            root_device: OnceLock::new(),
+            canonical_root: OnceLock::new(),
+        }
+    }
+
+    /// Walk `path` recursively, feeding every counted non-directory entry to `visitor`.
+    ///
+    /// Errors on `path` itself are always returned,
+    /// while errors on entries below it are handled according to the error policy.
+    pub(crate) fn run<V: Visitor>(&self, path: &Path, visitor: &V) -> io::Result<V::Acc> {
+        let path = &normalize(path);
+        let meta = fs::symlink_metadata(path)?;
+        #[cfg(unix)]
+        if self.options.stay_on_filesystem {
+            use std::os::unix::fs::MetadataExt;
+
+            self.root_device.get_or_init(|| meta.dev());
+        }
+        if meta.is_dir() {
+            let real = if self.options.follow_symlinks_within_root {
+                let canonical = fs::canonicalize(path)?;
+                self.canonical_root.get_or_init(|| canonical.clone());
+                Some(RealDir {
+                    path: canonical,
+                    parent: None,
+                })
+            } else {
+                None
+            };
+            Ok(self.walk_dir(path, &meta, visitor, real.as_ref())?.0)
+        } else {
+            let mut acc = visitor.init();
+            self.visit_entry(&mut acc, path, &meta, visitor)?;
+            Ok(acc)
+        }
+    }
+
+    /// Errors collected under [`ErrorPolicy::Collect`].
+    pub(crate) fn into_errors(self) -> Vec<(PathBuf, io::Error)> {
+        self.errors.into_inner().unwrap()
+    }
+
+    /// Number of entries that vanished during the walk and were not reported as errors.
+    pub(crate) fn races(&self) -> u64 {
+        self.races.load(Ordering::Relaxed)
+    }
+
+    /// Whether entries were skipped after reaching [`SizeOptions::max_entries`].
+    pub(crate) fn truncated(&self) -> bool {
+        self.truncated.load(Ordering::Relaxed)
+    }
+
+    /// Count one more visited entry, returning whether it is still within [`SizeOptions::max_entries`].
+    fn take_entry(&self) -> bool {
+        let Some(max_entries) = self.options.max_entries else {
+            return true;
+        };
+        if self.entries.fetch_add(1, Ordering::Relaxed) < max_entries {
+            return true;
+        }
+        self.truncated.store(true, Ordering::Relaxed);
+        false
+    }
+
+    /// Walk the directory at `path`, returning the accumulator and the size of its subtree.
+    ///
+    /// `real` is the canonical location of the directory when following symlinks.
+    fn walk_dir<V: Visitor>(
+        &self,
+        path: &Path,
+        meta: &Metadata,
+        visitor: &V,
+        real: Option<&RealDir<'_>>,
+    ) -> io::Result<(V::Acc, u64)> {
+        if visitor.is_done() || self.truncated() {
+            return Ok((visitor.init(), 0));
+        }
+        let mut read_dir = fs::read_dir(path)?;
+        // Skip setting up a parallel iteration for empty directories, common in some caches.
+        let Some(first) = read_dir.next() else {
+            return Ok(self.leave_dir((visitor.init(), 0), path, meta, visitor));
+        };
+        let entries = iter::once(first).chain(read_dir).collect();
+        self.walk_entries(path, meta, entries, visitor, real)
+    }
+
+    /// Walk the listed `entries` of the directory at `path`.
+    fn walk_entries<V: Visitor>(
+        &self,
+        path: &Path,
+        meta: &Metadata,
+        entries: Vec<io::Result<fs::DirEntry>>,
+        visitor: &V,
+        real: Option<&RealDir<'_>>,
+    ) -> io::Result<(V::Acc, u64)> {
+        let merge = |(a, a_bytes), (b, b_bytes)| (visitor.merge(a, b), a_bytes + b_bytes);
+        let subtree = entries
+            .into_par_iter()
+            .try_fold(
+                || (visitor.init(), 0),
+                |(mut acc, mut bytes), entry| -> io::Result<(V::Acc, u64)> {
+                    if visitor.is_done() {
+                        return Ok((acc, bytes));
+                    }
+                    if !self.take_entry() {
+                        return Ok((acc, bytes));
+                    }
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(e) => {
+                            self.handle_error(path, e)?;
+                            return Ok((acc, bytes));
+                        }
+                    };
+                    if self.options.exclude_hidden && is_hidden(&entry) {
+                        return Ok((acc, bytes));
+                    }
+                    let entry_path = entry.path();
+                    let meta = match entry.metadata() {
+                        Ok(meta) => meta,
+                        Err(e) => {
+                            self.handle_error(&entry_path, e)?;
+                            return Ok((acc, bytes));
+                        }
+                    };
+                    let (meta, target) = match real {
+                        Some(real) if meta.is_symlink() => self.follow(&entry_path, meta, real),
+                        _ => (meta, None),
+                    };
+                    if !meta.is_dir() {
+                        match self.visit_entry(&mut acc, &entry_path, &meta, visitor) {
+                            Ok(size) => bytes += size,
+                            Err(e) => self.handle_error(&entry_path, e)?,
+                        }
+                    } else if self.should_descend(&entry_path, &meta)
+                        && visitor.enter_dir(&entry_path)
+                    {
+                        let sub_real = real.map(|parent| RealDir {
+                            path: target.unwrap_or_else(|| parent.path.join(entry.file_name())),
+                            parent: Some(parent),
+                        });
+                        match self.walk_dir(&entry_path, &meta, visitor, sub_real.as_ref()) {
+                            Ok(sub) => return Ok(merge((acc, bytes), sub)),
+                            Err(e) => self.handle_error(&entry_path, e)?,
+                        }
+                    }
+                    Ok((acc, bytes))
+                },
+            )
+            .try_reduce(|| (visitor.init(), 0), |a, b| Ok(merge(a, b)))?;
+
+        Ok(self.leave_dir(subtree, path, meta, visitor))
+    }
+
+    /// Complete the walk of the directory at `path`, given the accumulator and size of its subtree.
+    fn leave_dir<V: Visitor>(
+        &self,
+        (mut acc, bytes): (V::Acc, u64),
+        path: &Path,
+        meta: &Metadata,
+        visitor: &V,
+    ) -> (V::Acc, u64) {
+        visitor.leave_dir(&mut acc, path, meta);
+        // Once entries are skipped, the size of a directory completed from then on may be partial.
+        if let Some(callback) = self
+            .options
+            .on_dir_complete
+            .as_ref()
+            .filter(|_| !self.truncated())
+        {
+            callback(path, bytes);
+        }
+        (acc, bytes)
+    }
+
+    /// Follow the symlink at `path` if its target stays within the walked path,
+    /// returning the metadata to walk it with, and its canonical target if followed.
+    ///
+    /// Symlinks leading out of the walked path, dangling or leading back into a directory
+    /// being walked, which would never end, are not followed and keep their own metadata.
+    fn follow(
+        &self,
+        path: &Path,
+        meta: Metadata,
+        real: &RealDir<'_>,
+    ) -> (Metadata, Option<PathBuf>) {
+        let Some(root) = self.canonical_root.get() else {
+            return (meta, None);
+        };
+        let Ok(target) = fs::canonicalize(path) else {
+            return (meta, None);
+        };
+        if !target.starts_with(root) {
+            return (meta, None);
+        }
+        match fs::metadata(&target) {
+            Ok(target_meta) if target_meta.is_dir() && real.is_within(&target) => (meta, None),
+            Ok(target_meta) => (target_meta, Some(target)),
+            Err(_) => (meta, None),
+        }
+    }
+
+    /// Whether the walk crosses into the directory at `path`.
+    fn should_descend(&self, path: &Path, meta: &Metadata) -> bool {
+        #[cfg(unix)]
+        if let Some(&root_device) = self.root_device.get() {
+            use std::os::unix::fs::MetadataExt;
+
+            if meta.dev() != root_device {
+                return false;
+            }
+        }
+        self.options
+            .descend_into
+            .as_ref()
+            .is_none_or(|filter| filter(path, meta))
+    }
+
+    /// Feed a non-directory entry to `visitor`, depending on whether it counts towards the size,
+    /// and return the size it counts with.
+    ///
+    /// Entries rejected by the filters of the options are skipped altogether, and so are
+    /// unreadable files when only counting readable ones, which returns why they are unreadable.
+    fn visit_entry<V: Visitor>(
+        &self,
+        acc: &mut V::Acc,
+        path: &Path,
+        meta: &Metadata,
+        visitor: &V,
+    ) -> io::Result<u64> {
+        if !self.options.matches(meta) {
+            return Ok(0);
+        }
+        if self.options.readable_only && meta.is_file() {
+            crate::readable::check_readable(path)?;
+        }
+        #[cfg(all(target_os = "linux", feature = "fifo"))]
+        if self.options.count_fifo_buffers && crate::fifo::is_fifo(meta) {
+            let size = crate::fifo::pending_bytes(path).unwrap_or(0);
+            visitor.visit_file(acc, path, meta, size);
+            return Ok(size);
+        }
+        let counted = if meta.is_symlink() {
+            self.options.count_symlink_size
+        } else {
+            meta.is_file() || self.options.fallback_to_len
+        };
+        if counted {
+            let size = self.size_of(path, meta);
+            visitor.visit_file(acc, path, meta, size);
+            Ok(size)
+        } else {
+            visitor.visit_uncounted(acc, path, meta);
+            Ok(0)
+        }
+    }
+
+    /// The size of a counted entry, as defined by [`SizeOptions::size_kind`].
+    fn size_of(&self, path: &Path, meta: &Metadata) -> u64 {
+        match self.options.size_kind {
+            SizeKind::Apparent => meta.len(),
+            #[cfg(unix)]
+            SizeKind::Allocated => {
+                use std::os::unix::fs::MetadataExt;
+
+                meta.blocks() * 512
+            }
+            #[cfg(not(unix))]
+            SizeKind::Allocated => meta.len(),
+            SizeKind::Physical if meta.is_file() => self
+                .shared_extents
+                .physical_len(path, meta)
+                .unwrap_or(meta.len()),
+            SizeKind::Physical => meta.len(),
+        }
+    }
+
+    /// Apply the error policy to an error on `path`, returning it only if the walk must stop.
+    ///
+    /// Entries not found anymore were removed after their directory was listed. Unless
+    /// configured otherwise, they are only counted as races, since they are not an error
+    /// of the walk but a consequence of walking a live filesystem.
+    fn handle_error(&self, path: &Path, error: io::Error) -> io::Result<()> {
+        if error.kind() == io::ErrorKind::NotFound && !self.options.vanished_as_errors {
+            self.races.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+        match &self.options.error_policy {
+            ErrorPolicy::Ignore => Ok(()),
+            ErrorPolicy::Collect => {
+                self.errors
+                    .lock()
+                    .unwrap()
+                    .push((path.to_path_buf(), error));
+                Ok(())
+            }
+            ErrorPolicy::FailFast => Err(error),
+            ErrorPolicy::Callback(callback) => {
+                callback(path, &error);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Whether `entry` is hidden: its name starts with a dot or, on Windows, it has the hidden attribute.
+fn is_hidden(entry: &fs::DirEntry) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        // The metadata of directory entries is cached on Windows, so this does not stat again.
+        if entry
+            .metadata()
+            .is_ok_and(|meta| meta.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+        {
+            return true;
+        }
+    }
+    entry.file_name().as_encoded_bytes().starts_with(b".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Sum;
+
+    impl Visitor for Sum {
+        type Acc = u64;
+
+        fn init(&self) -> u64 {
+            0
+        }
+
+        fn visit_file(&self, acc: &mut u64, _path: &Path, _meta: &Metadata, size: u64) {
+            *acc += size;
+        }
+
+        fn merge(&self, a: u64, b: u64) -> u64 {
+            a + b
+        }
+    }
+
+    /// Walk a directory whose `vanished` file is removed after listing it but before accessing it.
+    fn walk_with_vanished_file(options: &SizeOptions) -> (io::Result<u64>, u64) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("kept"), [0; 100]).unwrap();
+        fs::write(dir.path().join("vanished"), [0; 200]).unwrap();
+
+        let meta = fs::symlink_metadata(dir.path()).unwrap();
+        let entries = fs::read_dir(dir.path()).unwrap().collect();
+        fs::remove_file(dir.path().join("vanished")).unwrap();
+
+        let walker = Walker::new(options);
+        let result = walker.walk_entries(dir.path(), &meta, entries, &Sum, None);
+        (result.map(|(acc, _)| acc), walker.races())
+    }
+
+    #[test]
+    fn test_normalize() {
+        for (path, normalized) in [
+            ("dir", "dir"),
+            ("dir/", "dir"),
+            ("dir//sub///", "dir/sub"),
+            ("dir/.", "dir"),
+            ("./dir/./sub/.", "dir/sub"),
+            ("dir/..", "dir/.."),
+            ("../dir/../sub/", "../dir/../sub"),
+            (".", "."),
+            ("./", "."),
+            ("./.", "."),
+            ("/", "/"),
+            ("/.", "/"),
+            ("", ""),
+        ] {
+            assert_eq!(normalize(Path::new(path)), Path::new(normalized), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_empty_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..500 {
+            fs::create_dir_all(dir.path().join(format!("{i}/empty"))).unwrap();
+        }
+        fs::write(dir.path().join("0/file"), [0; 100]).unwrap();
+
+        assert_eq!(walk(dir.path(), &Sum).unwrap(), 100);
+        let usage = crate::get_usage(dir.path()).unwrap();
+        assert_eq!(usage.bytes, 100);
+        assert_eq!(usage.inodes, 1 + 500 * 2 + 1);
+        assert_eq!(walk(&dir.path().join("0/empty"), &Sum).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_vanished_entry() {
+        let options = SizeOptions::new().error_policy(ErrorPolicy::FailFast);
+        let (bytes, races) = walk_with_vanished_file(&options);
+        assert_eq!(bytes.unwrap(), 100);
+        assert_eq!(races, 1);
+
+        let options = options.vanished_as_errors(true);
+        let (bytes, races) = walk_with_vanished_file(&options);
+        assert_eq!(bytes.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(races, 0);
+    }
+}
@@ -0,0 +1,303 @@
+// SPDX-FileCopyrightText: 2024 Integral <integral@member.fsf.org>
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::walk::{normalize, walk, Visitor};
+use notify::{event::ModifyKind, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::{self, Metadata},
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// Keeps the total size of a watched tree up to date, see [`watch_size`].
+///
+/// Watching stops when this is dropped.
+pub struct SizeWatcher {
+    shared: Arc<Shared>,
+    _watcher: RecommendedWatcher,
+}
+
+impl fmt::Debug for SizeWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SizeWatcher")
+            .field("root", &self.shared.root)
+            .field("total", &self.total())
+            .finish_non_exhaustive()
+    }
+}
+
+impl SizeWatcher {
+    /// The current total size (in bytes), as last reported to the callback.
+    pub fn total(&self) -> u64 {
+        self.shared.state.lock().unwrap().total
+    }
+
+    /// Walk the whole tree again, replacing the total maintained from the events.
+    ///
+    /// Events can be coalesced or missed, so the maintained total may drift from the
+    /// actual size over time. Calling this periodically bounds the drift, at the cost
+    /// of a full walk. The callback is invoked if the total changes.
+    ///
+    /// This function will return an error if the watched path does not exist anymore,
+    /// or user lacks permissions to perform `metadata` call on it.
+    pub fn reconcile(&self) -> io::Result<()> {
+        self.shared.reconcile()
+    }
+}
+
+/// Watch the size of the file (in bytes), calling `callback` with the new total whenever it changes.
+///
+/// The tree is walked once like [`get_size_in_bytes`](crate::get_size_in_bytes) does, and
+/// `callback` is called with the initial total before this function returns. From then on,
+/// filesystem change events keep the total up to date without walking the tree again: a
+/// created or modified file is measured again, a removed one is subtracted, and a directory
+/// created or moved into the tree is walked. The events come from inotify on Linux,
+/// FSEvents on macOS and `ReadDirectoryChangesW` on Windows, through the `notify` crate.
+///
+/// The maintained total is only as accurate as the events: they can be coalesced, delayed
+/// or dropped, e.g. when the event queue of the kernel overflows, in which case the tree is
+/// walked again if the loss is reported. Changes made through paths outside of the tree,
+/// such as hard links, may not be reported at all. Call [`SizeWatcher::reconcile`]
+/// periodically to correct any drift.
+///
+/// `callback` is called from a thread of the watcher, and from [`SizeWatcher::reconcile`].
+/// Calls never overlap, but `callback` must not call the methods of the watcher, which would
+/// deadlock. Watching stops when the returned [`SizeWatcher`] is dropped.
+///
+/// The size of every file is remembered, so memory grows with the number of files.
+/// This is only available with the `watch` feature.
+///
+/// This function will return an error if `path` does not exist,
+/// or user lacks permissions to perform `metadata` call on `path`,
+/// or if the platform cannot watch `path`.
+pub fn watch_size(
+    path: &Path,
+    callback: impl Fn(u64) + Send + Sync + 'static,
+) -> io::Result<SizeWatcher> {
+    let root = normalize(path);
+    fs::symlink_metadata(&root)?;
+    let shared = Arc::new(Shared {
+        root,
+        state: Mutex::default(),
+        callback: Box::new(callback),
+    });
+
+    // Hold the state until the initial walk is complete, so that the events received
+    // meanwhile are applied on top of it.
+    let mut state = shared.state.lock().unwrap();
+    let handler = Arc::clone(&shared);
+    let mut watcher =
+        notify::recommended_watcher(move |event| handler.handle(event)).map_err(into_io_error)?;
+    watcher
+        .watch(&shared.root, RecursiveMode::Recursive)
+        .map_err(into_io_error)?;
+    state.refresh(&shared.root)?;
+    (shared.callback)(state.total);
+    drop(state);
+
+    Ok(SizeWatcher {
+        shared,
+        _watcher: watcher,
+    })
+}
+
+fn into_io_error(e: notify::Error) -> io::Error {
+    match e.kind {
+        notify::ErrorKind::Io(e) => e,
+        _ => io::Error::other(e),
+    }
+}
+
+struct Shared {
+    root: PathBuf,
+    state: Mutex<State>,
+    callback: Box<dyn Fn(u64) + Send + Sync>,
+}
+
+impl Shared {
+    /// Apply `update` to the state, and report the new total if it changed.
+    fn update<T>(&self, update: impl FnOnce(&mut State) -> T) -> T {
+        let mut state = self.state.lock().unwrap();
+        let before = state.total;
+        let result = update(&mut state);
+        if state.total != before {
+            (self.callback)(state.total);
+        }
+        result
+    }
+
+    fn reconcile(&self) -> io::Result<()> {
+        self.update(|state| {
+            let mut fresh = State::default();
+            fresh.refresh(&self.root)?;
+            *state = fresh;
+            Ok(())
+        })
+    }
+
+    fn handle(&self, event: notify::Result<Event>) {
+        let event = match event {
+            Ok(event) if !event.need_rescan() => event,
+            // Events may have been lost, so only a new walk gives the right total.
+            _ => {
+                let _ = self.reconcile();
+                return;
+            }
+        };
+        self.update(|state| {
+            for path in &event.paths {
+                let is_dir = fs::symlink_metadata(path).is_ok_and(|meta| meta.is_dir());
+                // The entries of a directory already known are reported on their own,
+                // so only directories new to the tree need to be walked.
+                let new_entries = matches!(
+                    event.kind,
+                    EventKind::Create(_)
+                        | EventKind::Modify(ModifyKind::Name(_))
+                        | EventKind::Any
+                        | EventKind::Other
+                );
+                if !is_dir || new_entries {
+                    let _ = state.refresh(path);
+                }
+            }
+        });
+    }
+}
+
+/// The sizes of the files of the watched tree, and its directories.
+#[derive(Default)]
+struct State {
+    files: HashMap<PathBuf, u64>,
+    dirs: HashSet<PathBuf>,
+    total: u64,
+}
+
+impl State {
+    /// Replace everything known at or below `path` with what is found there now.
+    fn refresh(&mut self, path: &Path) -> io::Result<()> {
+        self.remove(path);
+        let found = walk(path, &Tree)?;
+        for (file, size) in found.files {
+            self.total += size;
+            if let Some(old) = self.files.insert(file, size) {
+                self.total -= old;
+            }
+        }
+        self.dirs.extend(found.dirs);
+        Ok(())
+    }
+
+    /// Forget everything known at or below `path`.
+    fn remove(&mut self, path: &Path) {
+        if let Some(size) = self.files.remove(path) {
+            self.total -= size;
+        }
+        // Files are only looked for below known directories, so that changes
+        // to single files do not go through every file of the tree.
+        if self.dirs.remove(path) {
+            let total = &mut self.total;
+            self.files.retain(|file, size| {
+                let below = file.starts_with(path);
+                if below {
+                    *total -= *size;
+                }
+                !below
+            });
+            self.dirs.retain(|dir| !dir.starts_with(path));
+        }
+    }
+}
+
+struct Tree;
+
+impl Visitor for Tree {
+    type Acc = State;
+
+    fn init(&self) -> State {
+        State::default()
+    }
+
+    fn visit_file(&self, acc: &mut State, path: &Path, _meta: &Metadata, size: u64) {
+        acc.files.insert(path.to_path_buf(), size);
+    }
+
+    fn leave_dir(&self, acc: &mut State, path: &Path, _meta: &Metadata) {
+        acc.dirs.insert(path.to_path_buf());
+    }
+
+    fn merge(&self, mut a: State, b: State) -> State {
+        a.files.extend(b.files);
+        a.dirs.extend(b.dirs);
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::mpsc,
+        time::{Duration, Instant},
+    };
+
+    #[test]
+    fn test_state() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("sub/deep")).unwrap();
+        fs::write(dir.path().join("file"), [0; 1]).unwrap();
+        fs::write(dir.path().join("sub/file"), [0; 10]).unwrap();
+        fs::write(dir.path().join("sub/deep/file"), [0; 100]).unwrap();
+
+        let mut state = State::default();
+        state.refresh(dir.path()).unwrap();
+        assert_eq!(state.total, 111);
+
+        fs::write(dir.path().join("sub/file"), [0; 20]).unwrap();
+        state.refresh(&dir.path().join("sub/file")).unwrap();
+        assert_eq!(state.total, 121);
+
+        fs::remove_dir_all(dir.path().join("sub")).unwrap();
+        state.refresh(&dir.path().join("sub")).unwrap_err();
+        assert_eq!(state.total, 1);
+        assert_eq!(state.files.len(), 1);
+        assert_eq!(state.dirs.len(), 1);
+    }
+
+    #[test]
+    fn test_watch_size() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file"), [0; 100]).unwrap();
+
+        let (sender, totals) = mpsc::channel();
+        let watcher = watch_size(dir.path(), move |total| sender.send(total).unwrap()).unwrap();
+        assert_eq!(totals.recv().unwrap(), 100);
+
+        let wait_for = |expected: u64| {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while let Ok(total) =
+                totals.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            {
+                if total == expected {
+                    return;
+                }
+            }
+            panic!("the total never reached {expected}");
+        };
+
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/file"), [0; 10]).unwrap();
+        wait_for(110);
+        fs::write(dir.path().join("file"), [0; 50]).unwrap();
+        wait_for(60);
+        fs::remove_dir_all(dir.path().join("sub")).unwrap();
+        wait_for(50);
+        assert_eq!(watcher.total(), 50);
+
+        watcher.reconcile().unwrap();
+        assert_eq!(watcher.total(), 50);
+    }
+}